@@ -1,11 +1,140 @@
-use std::{
-    cell::{Ref, RefCell, RefMut},
+use alloc::{
+    boxed::Box,
+    collections::VecDeque,
+    format,
+    rc::{self, Rc},
+    string::{String, ToString},
+    sync::{self, Arc},
+    vec,
+    vec::Vec,
+};
+use core::{
+    any,
+    cell::{Cell, Ref, RefCell, RefMut},
+    error::Error,
+    fmt::{self, Write as _},
+    marker::PhantomData,
+    mem,
     ops::{Deref, DerefMut},
-    rc::Rc,
-    sync::Arc,
+    sync::atomic::{self, AtomicUsize},
 };
 
-use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(any(debug_assertions, not(feature = "std")))]
+use core::sync::atomic::AtomicBool;
+#[cfg(debug_assertions)]
+use core::sync::atomic::Ordering;
+
+#[cfg(feature = "std")]
+use crate::lock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(feature = "std")]
+use std::collections::{HashMap as Map, HashSet as Set};
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::{BTreeMap as Map, BTreeSet as Set};
+
+/// Emits a trace level `tracing` event with the `tracing` feature, and
+/// nothing otherwise, so its fields are not even evaluated.
+macro_rules! trace {
+    ($($event:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($event)*);
+    };
+}
+
+/// Program wide state standing in for thread locals without `std`.
+///
+/// There are no threads to tell apart without `std`, so the state is shared
+/// by the whole program, and it is only reachable once the program promised
+/// to use signals from a single execution context, see
+/// `assume_single_context`.
+#[cfg(not(feature = "std"))]
+struct Global<T>(T);
+
+/// Whether `assume_single_context` was called.
+#[cfg(not(feature = "std"))]
+static SINGLE_CONTEXT: AtomicBool = AtomicBool::new(false);
+
+// SAFETY: The contents are only reached through `Global::get`, which panics
+// until `assume_single_context` was called, and its caller guarantees that
+// they are used from one execution context from then on.
+#[cfg(not(feature = "std"))]
+unsafe impl<T> Sync for Global<T> {}
+
+#[cfg(not(feature = "std"))]
+impl<T> Global<T> {
+    fn get(&self) -> &T {
+        assert!(
+            SINGLE_CONTEXT.load(atomic::Ordering::Acquire),
+            "signals need `api::assume_single_context` without `std`"
+        );
+        &self.0
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(self.get())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<T> Global<RefCell<T>> {
+    fn read(&self) -> Ref<'_, T> {
+        self.get().borrow()
+    }
+
+    fn write(&self) -> RefMut<'_, T> {
+        self.get().borrow_mut()
+    }
+}
+
+/// Allows signals to be used without `std`.
+///
+/// Without `std` the propagation state, which is otherwise kept per thread,
+/// is shared by the whole program, so every signal operation panics until
+/// this is called. With `std` this does nothing, so code built either way can
+/// call it.
+///
+/// # Safety
+///
+/// Without `std`, signals must only be used from one execution context from
+/// the call on. They must not be used from other threads or cores, nor from
+/// an interrupt handler that may run while the rest of the program uses them.
+///
+/// # Example
+/// ```
+/// use reactivity::{api::SignalTrait, Signal};
+///
+/// // SAFETY: The program uses signals from its main loop only.
+/// unsafe { reactivity::api::assume_single_context() };
+///
+/// let count = Signal::new(1);
+/// assert_eq!(count.get(), 1);
+/// ```
+pub unsafe fn assume_single_context() {
+    #[cfg(not(feature = "std"))]
+    SINGLE_CONTEXT.store(true, atomic::Ordering::Release);
+}
+
+/// Declares the propagation state as `Global` statics without `std`.
+#[cfg(not(feature = "std"))]
+macro_rules! thread_local {
+    () => {};
+    ($(#[$attr:meta])* static $name:ident: $ty:ty = const $init:block; $($rest:tt)*) => {
+        $(#[$attr])* static $name: Global<$ty> = Global($init);
+        thread_local!($($rest)*);
+    };
+    ($(#[$attr:meta])* static $name:ident: $ty:ty = $init:expr; $($rest:tt)*) => {
+        $(#[$attr])* static $name: Global<$ty> = Global($init);
+        thread_local!($($rest)*);
+    };
+}
+
+/// Diagnostics have nowhere to go without `std`.
+#[cfg(not(feature = "std"))]
+macro_rules! eprintln {
+    ($($arg:tt)*) => {{
+        let _ = format_args!($($arg)*);
+    }};
+}
 
 /// A trait for shared pointer types.
 ///
@@ -15,15 +144,28 @@ pub trait Shared: Clone + AsRef<Self::Inner> + Deref<Target = Self::Inner> {
     type Inner: ?Sized;
     /// The equivalent shared pointer type for a different inner type.
     type M<U: ?Sized>: Shared<Inner = U>;
+    /// The weak pointer type that does not keep the value alive.
+    type Weak: Clone;
     /// Creates a new instance of the shared pointer with the given value.
     fn new(value: Self::Inner) -> Self
     where
         Self::Inner: Sized;
+    /// Creates a weak pointer to the same value.
+    fn downgrade(this: &Self) -> Self::Weak;
+    /// Upgrades a weak pointer, returning `None` if the value has been dropped.
+    fn upgrade(weak: &Self::Weak) -> Option<Self>;
+    /// Returns whether the value a weak pointer points to still exists.
+    fn is_alive(weak: &Self::Weak) -> bool;
+    /// Returns the number of shared pointers to the value.
+    fn strong_count(this: &Self) -> usize;
+    /// Returns the number of weak pointers to the value.
+    fn weak_count(this: &Self) -> usize;
 }
 
 impl<T: ?Sized> Shared for Rc<T> {
     type Inner = T;
     type M<U: ?Sized> = Rc<U>;
+    type Weak = rc::Weak<T>;
 
     fn new(value: T) -> Self
     where
@@ -31,11 +173,32 @@ impl<T: ?Sized> Shared for Rc<T> {
     {
         Rc::new(value)
     }
+
+    fn downgrade(this: &Self) -> Self::Weak {
+        Rc::downgrade(this)
+    }
+
+    fn upgrade(weak: &Self::Weak) -> Option<Self> {
+        weak.upgrade()
+    }
+
+    fn is_alive(weak: &Self::Weak) -> bool {
+        weak.strong_count() > 0
+    }
+
+    fn strong_count(this: &Self) -> usize {
+        Rc::strong_count(this)
+    }
+
+    fn weak_count(this: &Self) -> usize {
+        Rc::weak_count(this)
+    }
 }
 
 impl<T: ?Sized> Shared for Arc<T> {
     type Inner = T;
     type M<U: ?Sized> = Arc<U>;
+    type Weak = sync::Weak<T>;
 
     fn new(value: T) -> Self
     where
@@ -43,6 +206,26 @@ impl<T: ?Sized> Shared for Arc<T> {
     {
         Arc::new(value)
     }
+
+    fn downgrade(this: &Self) -> Self::Weak {
+        Arc::downgrade(this)
+    }
+
+    fn upgrade(weak: &Self::Weak) -> Option<Self> {
+        weak.upgrade()
+    }
+
+    fn is_alive(weak: &Self::Weak) -> bool {
+        weak.strong_count() > 0
+    }
+
+    fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(this)
+    }
+
+    fn weak_count(this: &Self) -> usize {
+        Arc::weak_count(this)
+    }
 }
 
 /// A trait for types that provide interior mutability.
@@ -72,6 +255,10 @@ pub trait InternalMutable {
     /// Borrows the inner value as an immutable reference.
     fn borrow(&self) -> Self::Ref<'_>;
 
+    /// Borrows the inner value immutably, returning `None` instead of
+    /// panicking or blocking while it is mutably borrowed.
+    fn try_borrow(&self) -> Option<Self::Ref<'_>>;
+
     /// Borrows the inner value as a mutable reference.
     fn borrow_mut(&self) -> Self::RefMut<'_>;
 
@@ -122,11 +309,16 @@ impl<T: ?Sized> InternalMutable for RefCell<T> {
         RefCell::borrow(self)
     }
 
+    fn try_borrow(&self) -> Option<Self::Ref<'_>> {
+        RefCell::try_borrow(self).ok()
+    }
+
     fn borrow_mut(&self) -> Self::RefMut<'_> {
         RefCell::borrow_mut(self)
     }
 }
 
+#[cfg(feature = "std")]
 impl<T: ?Sized> InternalMutable for RwLock<T> {
     type Inner = T;
     type Ref<'a>
@@ -148,7 +340,13 @@ impl<T: ?Sized> InternalMutable for RwLock<T> {
     }
 
     fn borrow(&self) -> Self::Ref<'_> {
-        self.read()
+        // Effects may read a signal that is already read locked by the
+        // thread, see `sync::set_scheduler`, which must not wait for writers.
+        self.read_recursive()
+    }
+
+    fn try_borrow(&self) -> Option<Self::Ref<'_>> {
+        self.try_read()
     }
 
     fn borrow_mut(&self) -> Self::RefMut<'_> {
@@ -158,8 +356,10 @@ impl<T: ?Sized> InternalMutable for RwLock<T> {
 
 /// A promise that resolves when an update to a receptive signal is completed.
 ///
-/// When a signal is updated, it creates an `UpdatePromise` for itself and all its
-/// dependents. These promises ensure that all signals are updated in the correct order.
+/// When a signal is updated, it marks all of its dependents as pending and
+/// creates an `UpdatePromise` for each of its direct receivers. A signal reacts
+/// once the promises from all of its pending dependencies have resolved, which
+/// ensures that all signals are updated in the correct order.
 /// When the promise is dropped, it automatically resolves by calling the `resolve` method.
 pub struct UpdatePromise(pub(crate) Box<dyn Receptive>);
 
@@ -168,230 +368,2977 @@ impl UpdatePromise {
     ///
     /// If the counter reaches zero, triggers the signal's reaction.
     pub fn resolve(&self) {
+        // Promises resolved by `settle` queue the next ones, any other promise
+        // is handed to `settle`, so reactions always run inside one.
+        let queued = QUEUED.with(|queued| queued.replace(false));
+        if !queued {
+            settle(vec![UpdatePromise(self.0.clone_boxed())]);
+            return;
+        }
         self.0.decrease();
         if self.0.count() == 0 {
-            let _ = self.0.react();
+            enqueue(self.0.react());
         }
     }
 }
 
 impl Drop for UpdatePromise {
     fn drop(&mut self) {
+        // Resolving while an effect panic unwinds would run more effects, and
+        // a second panic aborts. The pending signals are reset instead, so
+        // later updates are not stuck waiting for them.
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            abandon(&*self.0);
+            return;
+        }
+        if ABANDONING.with(Cell::get) {
+            abandon(&*self.0);
+            return;
+        }
         self.resolve();
     }
 }
 
-/// A trait for tracking the dirty state of reactive components.
+thread_local! {
+    /// Ids of signals with a dependency that changed since they last reacted.
+    static STALE: RefCell<Set<usize>> = RefCell::new(Set::new());
+    /// Number of dependency updates each pending signal waits for, by id.
+    ///
+    /// Kept per thread like the rest of the propagation state, so updates
+    /// sent from different threads to a `sync::Signal` settle independently
+    /// instead of waiting on marks only the other thread can resolve.
+    static DIRTY: RefCell<Map<usize, usize>> = RefCell::new(Map::new());
+    /// Number of `Batch` guards alive on this thread.
+    static BATCH_DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// Promises withheld until the outermost batch closes.
+    static BATCHED: RefCell<Vec<UpdatePromise>> = const { RefCell::new(Vec::new()) };
+    /// Receivers marked by each pending signal, resolved once it settles.
+    static MARKED: RefCell<Map<usize, Vec<Box<dyn Receptive>>>> = RefCell::new(Map::new());
+    /// Signals whose receivers are being marked, outermost first.
+    static MARKING: RefCell<Vec<Box<dyn Receptive>>> = const { RefCell::new(Vec::new()) };
+    /// Ids of the signals in `MARKING`.
+    static ON_PATH: RefCell<Set<usize>> = RefCell::new(Set::new());
+    /// Order in which updates sent from this thread reach receivers.
+    static ORDER: Cell<Order> = const { Cell::new(Order::DepthFirst) };
+    /// Whether receivers are sorted by creation, see `set_deterministic`.
+    static DETERMINISTIC: Cell<bool> = const { Cell::new(false) };
+    /// Promises waiting to resolve, one queue per `settle` in progress.
+    static QUEUES: RefCell<Vec<VecDeque<UpdatePromise>>> = const { RefCell::new(Vec::new()) };
+    /// Signals read by each tracked function running, innermost last, see
+    /// `tracked`. Frames pushed by `untrack` record nothing.
+    static TRACKING: RefCell<Vec<Option<Reads>>> = const { RefCell::new(Vec::new()) };
+    /// Whether the promise being dropped was taken from a queue by `settle`.
+    static QUEUED: Cell<bool> = const { Cell::new(false) };
+    /// Promises of updates sent while another update was settling.
+    static RESENT: RefCell<Vec<UpdatePromise>> = const { RefCell::new(Vec::new()) };
+    /// Whether promises are dropped without resolving, see `settle`.
+    static ABANDONING: Cell<bool> = const { Cell::new(false) };
+    /// Functions of dropped signals left to drop, see `State::drop`.
+    static GARBAGE: RefCell<Vec<Box<dyn any::Any>>> = const { RefCell::new(Vec::new()) };
+    /// Whether a signal is dropping the functions in `GARBAGE`.
+    static COLLECTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The order in which an update reaches the receivers of a signal.
 ///
-/// Components with a dirty count > 0 are pending updates.
-pub(crate) trait Dirty {
-    /// Gets the current dirty counter value.
-    fn count(&self) -> usize;
-    /// Increases the dirty counter.
-    fn increase(&self);
-    /// Decreases the dirty counter, clamping at zero.
-    fn decrease(&self);
+/// Both orders are glitch-free: a signal reacts only once all of its pending
+/// dependencies have settled, so the final values are the same either way.
+/// The order decides which ready signals react first, and so the order in
+/// which effects run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Order {
+    /// Each receiver's own receivers update before its siblings do.
+    ///
+    /// Effects run in the order the receiver lists are walked, so a chain of
+    /// signals updates from start to end before the next branch.
+    #[default]
+    DepthFirst,
+    /// All direct receivers update before any of their receivers.
+    ///
+    /// Effects run level by level, which suits wide graphs.
+    BreadthFirst,
 }
 
-impl<T> Dirty for T
-where
-    T: Receptive + SealedSignalTrait + 'static,
-{
-    fn count(&self) -> usize {
-        *self.dirty().borrow()
+/// Sets the order of propagation for updates sent from the current thread.
+///
+/// Like `Batch`, the setting is per thread. The default is
+/// `Order::DepthFirst`.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{api::SignalTrait, set_propagation_order, signal, Order, Signal};
+///
+/// set_propagation_order(Order::BreadthFirst);
+/// let x = signal!(1);
+/// let doubled = signal!([x] x * 2);
+/// let quadrupled = signal!([doubled] doubled * 2);
+///
+/// x.send(2);
+/// assert_eq!(quadrupled.get(), 8);
+/// ```
+pub fn set_propagation_order(order: Order) {
+    ORDER.with(|current| current.set(order));
+}
+
+/// Returns the order of propagation on the current thread, see
+/// `set_propagation_order`.
+pub fn propagation_order() -> Order {
+    ORDER.with(Cell::get)
+}
+
+/// Sets whether updates sent from the current thread reach receivers in the
+/// order the receivers were created.
+///
+/// By default, receivers of equal priority are updated in the order they
+/// were added, which depends on how the graph was assembled. In deterministic
+/// mode they are updated in the order the receiving signals were created
+/// instead, so the order in which effects run stays the same when the code
+/// adding receivers is rearranged. Receivers of a higher priority still come
+/// first.
+///
+/// Each signal that propagates an update then sorts its receivers, which
+/// costs `O(n log n)` for `n` receivers on every send. Like `Batch`, the
+/// setting is per thread, and it is off by default.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use std::{cell::RefCell, rc::Rc};
+/// use reactivity::{api::SignalTrait, set_deterministic, FnReceiver, Signal};
+///
+/// set_deterministic(true);
+/// let log = Rc::new(RefCell::new(Vec::new()));
+/// let receiver = |name| {
+///     let log = log.clone();
+///     FnReceiver::new(move || log.borrow_mut().push(name))
+/// };
+/// let (first, second) = (receiver("first"), receiver("second"));
+/// let x = Signal::new(0);
+/// x.add_receiver(second);
+/// x.add_receiver(first);
+///
+/// x.send(1);
+/// assert_eq!(*log.borrow(), ["first", "second"]);
+/// # set_deterministic(false);
+/// ```
+pub fn set_deterministic(enabled: bool) {
+    DETERMINISTIC.with(|current| current.set(enabled));
+}
+
+/// Returns whether receivers are updated in the order of their creation on
+/// the current thread, see `set_deterministic`.
+pub fn is_deterministic() -> bool {
+    DETERMINISTIC.with(Cell::get)
+}
+
+/// Position of the next signal in the order of creation.
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// Adds promises to the queue of the innermost `settle`, in the order of
+/// propagation of the current thread.
+///
+/// Depth-first, they go to the front so they resolve before the promises
+/// already waiting. Breadth-first, they go to the back.
+fn enqueue(promises: Vec<UpdatePromise>) {
+    QUEUES.with(|queues| {
+        let mut queues = queues.borrow_mut();
+        let queue = queues.last_mut().unwrap();
+        match propagation_order() {
+            Order::DepthFirst => promises
+                .into_iter()
+                .rev()
+                .for_each(|promise| queue.push_front(promise)),
+            Order::BreadthFirst => queue.extend(promises),
+        }
+    });
+}
+
+/// Removes the queue of a `settle` when dropped, also when unwinding.
+struct Settling;
+
+impl Drop for Settling {
+    fn drop(&mut self) {
+        // The remaining promises resolve once their queue is gone, so they
+        // settle on their own.
+        let queue = QUEUES.with(|queues| queues.borrow_mut().pop());
+        drop(queue);
+        // Updates sent before an effect panicked are reset with the rest.
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            drop(RESENT.with(|resent| mem::take(&mut *resent.borrow_mut())));
+        }
     }
+}
 
-    fn increase(&self) {
-        *self.dirty().borrow_mut() += 1;
+/// Returns whether an update is settling on the current thread.
+fn is_settling() -> bool {
+    QUEUES.with(|queues| !queues.borrow().is_empty())
+}
+
+/// Resolves promises and everything they cause to react.
+///
+/// Instead of resolving the promises of a reacting signal from within its
+/// reaction, which nests as deep as the graph, they are queued and resolved
+/// in a loop.
+///
+/// Updates sent from an effect, or from anything else running while an
+/// update settles, would react in the middle of it, and a signal sending to
+/// its own source could then store a value older than the one its nested
+/// reaction stored. Their promises are set aside instead and settle in
+/// another round once the current one is done, until no more are sent. An
+/// effect that keeps sending gives up after `max_depth` rounds, leaving the
+/// last values stored but their receivers alone.
+fn settle(promises: Vec<UpdatePromise>) {
+    if is_settling() {
+        RESENT.with(|resent| resent.borrow_mut().extend(promises));
+        return;
+    }
+    let mut promises = promises;
+    let mut rounds = 0;
+    while !promises.is_empty() {
+        if rounds == max_depth() {
+            report_depth(DepthError::Rounds { rounds });
+            ABANDONING.with(|abandoning| abandoning.set(true));
+            drop(promises);
+            ABANDONING.with(|abandoning| abandoning.set(false));
+            return;
+        }
+        QUEUES.with(|queues| queues.borrow_mut().push(VecDeque::new()));
+        let settling = Settling;
+        enqueue(promises);
+        while let Some(promise) =
+            QUEUES.with(|queues| queues.borrow_mut().last_mut().unwrap().pop_front())
+        {
+            QUEUED.with(|queued| queued.set(true));
+            drop(promise);
+        }
+        drop(settling);
+        promises = RESENT.with(|resent| mem::take(&mut *resent.borrow_mut()));
+        rounds += 1;
     }
+    trace!(rounds, "update settled");
+}
 
-    fn decrease(&self) {
-        *self.dirty().borrow_mut() = self.count().saturating_sub(1);
+/// Keeps a signal on the marking path until dropped, also when unwinding.
+struct Marking {
+    id: usize,
+}
+
+impl Marking {
+    fn enter(signal: Box<dyn Receptive>) -> Self {
+        let id = signal.id();
+        MARKING.with(|marking| marking.borrow_mut().push(signal));
+        ON_PATH.with(|on_path| on_path.borrow_mut().insert(id));
+        Marking { id }
+    }
+
+    /// Returns the number of signals on the marking path.
+    fn depth() -> usize {
+        MARKING.with(|marking| marking.borrow().len())
     }
 }
 
-/// Core trait for reactive components.
-///
-/// Objects implementing this trait can react to changes and trigger reactions
-/// in dependent components.
-#[allow(private_bounds)]
-pub trait Receptive: Dirty {
-    /// Update the signal and trigger its reaction.
-    fn react(&self) -> Vec<UpdatePromise>;
+impl Drop for Marking {
+    fn drop(&mut self) {
+        MARKING.with(|marking| marking.borrow_mut().pop());
+        ON_PATH.with(|on_path| on_path.borrow_mut().remove(&self.id));
+    }
+}
 
-    /// Create promises for this signal and all its receivers.
-    ///
-    /// Called when a dependency changes to schedule updates.
-    fn promise(&self) -> Vec<UpdatePromise>;
+/// A signal whose receivers are being marked by `mark_graph`.
+struct Frame {
+    signal: Box<dyn Receptive>,
+    receivers: Vec<Box<dyn Receptive>>,
+    /// Index of the next receiver to mark
+    next: usize,
+    _marking: Marking,
+}
 
-    /// Clone this object as a trait object.
-    fn clone_boxed(&self) -> Box<dyn Receptive>;
+impl Frame {
+    /// Marks `signal`, returning a frame for its receivers if they need to be
+    /// marked too.
+    fn enter(signal: Box<dyn Receptive>) -> Option<Self> {
+        // Reaching a signal whose receivers are still being marked means the
+        // update would wait on itself forever.
+        if ON_PATH.with(|on_path| on_path.borrow().contains(&signal.id())) {
+            let cycle = MARKING.with(|marking| CycleError::closed_by(&marking.borrow(), &*signal));
+            panic!("{}", cycle.unwrap());
+        }
+        let receivers = signal.enter_mark()?;
+        // Receivers past the limit are left alone, so the update stops here.
+        let depth = Marking::depth();
+        if depth >= max_depth() {
+            report_depth(DepthError::Signals {
+                signal: signal.label(),
+                depth,
+            });
+            return None;
+        }
+        let marking = Marking::enter(signal.clone_boxed());
+        Some(Frame {
+            signal,
+            receivers,
+            next: 0,
+            _marking: marking,
+        })
+    }
 }
 
-impl<T> Receptive for T
-where
-    T: SealedSignalTrait + 'static,
-{
-    fn react(&self) -> Vec<UpdatePromise> {
-        let value = (self.processor().unwrap())();
-        (self.effect().unwrap())(&self, &value);
-        self.send(value)
+/// Marks a signal and, on its first mark, everything depending on it.
+///
+/// The graph is walked depth-first with an explicit stack, so deep graphs do
+/// not grow the call stack. Each signal remembers exactly who it marked, so
+/// receivers added or removed while the update is in flight do not unbalance
+/// the counters. The snapshot also keeps receivers alive until they have
+/// reacted.
+fn mark_graph(signal: Box<dyn Receptive>) {
+    let mut stack = Vec::from_iter(Frame::enter(signal));
+    while let Some(frame) = stack.last_mut() {
+        match frame.receivers.get(frame.next) {
+            Some(receiver) => {
+                let receiver = receiver.clone_boxed();
+                frame.next += 1;
+                stack.extend(Frame::enter(receiver));
+            }
+            None => {
+                let frame = stack.pop().unwrap();
+                let id = frame.signal.id();
+                MARKED.with(|marked| marked.borrow_mut().insert(id, frame.receivers));
+            }
+        }
     }
+}
 
-    fn promise(&self) -> Vec<UpdatePromise> {
-        self.increase();
-        let mut promises = self
-            .receivers()
-            .borrow()
+/// Error describing a cycle in the dependency graph.
+///
+/// Updates cannot settle in a cycle, since every signal in it waits for the
+/// others. `send` panics with this error, `send_checked` returns it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    /// Labels of the signals in the cycle, starting and ending with the same one.
+    pub cycle: Vec<String>,
+}
+
+impl CycleError {
+    /// Returns the cycle closed by reaching `signal` from the end of `path`.
+    fn closed_by(path: &[Box<dyn Receptive>], signal: &dyn Receptive) -> Option<Self> {
+        let start = path.iter().position(|node| node.id() == signal.id())?;
+        let mut cycle = path[start..]
             .iter()
-            .flat_map(|receiver| receiver.promise())
+            .map(|node| node.label())
             .collect::<Vec<_>>();
-        promises.insert(0, UpdatePromise(self.clone_boxed()));
-        promises
+        cycle.push(signal.label());
+        Some(CycleError { cycle })
     }
 
-    fn clone_boxed(&self) -> Box<dyn Receptive> {
-        Box::new(Self::init(
-            self.inner().clone(),
-            self.effect().cloned(),
-            self.processor().cloned(),
-            self.receivers().clone(),
-            self.dirty().clone(),
-        ))
+    /// Searches the receivers reachable from `signal` for a cycle.
+    fn find(signal: Box<dyn Receptive>) -> Option<Self> {
+        fn visit(
+            signal: Box<dyn Receptive>,
+            path: &mut Vec<Box<dyn Receptive>>,
+            visited: &mut Set<usize>,
+        ) -> Option<CycleError> {
+            if let Some(cycle) = CycleError::closed_by(path, &*signal) {
+                return Some(cycle);
+            }
+            if !visited.insert(signal.id()) {
+                return None;
+            }
+            let dependents = signal.dependents();
+            path.push(signal);
+            let cycle = dependents
+                .into_iter()
+                .find_map(|dependent| visit(dependent, path, visited));
+            path.pop();
+            cycle
+        }
+        visit(signal, &mut Vec::new(), &mut Set::new())
     }
 }
 
-/// Internal trait for signal implementation details.
-///
-/// This trait provides access to the internal fields of a signal.
-pub(crate) trait SealedSignalTrait: Receptive {
-    /// The type of the inner value.
-    type Inner;
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "signal dependency cycle: {}", self.cycle.join(" -> "))
+    }
+}
 
-    /// The type of shared pointer.
-    type Rc<U: ?Sized>: Shared<Inner = U>;
+impl Error for CycleError {}
 
-    /// The type that provides interior mutability.
-    type Ptr<U>: InternalMutable<Inner = U>;
+/// Error returned when a signal's value is mutably borrowed.
+///
+/// For `sync::Signal` this means another writer holds the lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BorrowError;
 
-    /// The type of function that produces values.
-    type Processor: Fn() -> Self::Inner + ?Sized;
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "signal value is already mutably borrowed")
+    }
+}
 
-    /// The type of function called when a new value is processed.
-    type Effect: Fn(&Self, &Self::Inner) + ?Sized;
+impl Error for BorrowError {}
 
-    /// The type of receivers that can depend on this signal.
-    type Receiver: Receptive + ?Sized;
+/// Error describing an update cut short by the limit of `set_max_depth`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepthError {
+    /// The update reached a signal this many signals deep, and its receivers
+    /// were left alone.
+    Signals {
+        /// Label of the last signal updated
+        signal: String,
+        /// Number of signals on the way to it
+        depth: usize,
+    },
+    /// Effects kept sending updates for this many rounds, and the last ones
+    /// were not propagated.
+    Rounds {
+        /// Number of rounds settled
+        rounds: usize,
+    },
+}
 
-    /// Initialize a new signal with the given components.
-    fn init(
-        inner: Self::Rc<Self::Ptr<Self::Inner>>,
-        effect: Option<Self::Rc<Self::Effect>>,
-        processor: Option<Self::Rc<Self::Processor>>,
-        receivers: Self::Rc<Self::Ptr<Vec<Box<Self::Receiver>>>>,
-        dirty: Self::Rc<Self::Ptr<usize>>,
-    ) -> Self;
+impl fmt::Display for DepthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DepthError::Signals { signal, depth } => write!(
+                f,
+                "update stopped at `{signal}`, {depth} signals deep, see `set_max_depth`"
+            ),
+            DepthError::Rounds { rounds } => write!(
+                f,
+                "effects kept sending updates for {rounds} rounds, see `set_max_depth`"
+            ),
+        }
+    }
+}
 
-    /// Access the inner value container.
-    fn inner(&self) -> &Self::Rc<Self::Ptr<Self::Inner>>;
+impl Error for DepthError {}
 
-    /// Access the effect function if present.
-    fn effect(&self) -> Option<&Self::Rc<Self::Effect>>;
+/// Default of `set_max_depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
 
-    /// Access the processor function if present.
-    fn processor(&self) -> Option<&Self::Rc<Self::Processor>>;
+static MAX_DEPTH: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_DEPTH);
 
-    /// Access the receivers list.
-    fn receivers(&self) -> &Self::Rc<Self::Ptr<Vec<Box<Self::Receiver>>>>;
+/// Sets how many signals deep an update may propagate.
+///
+/// An update reaches receivers at most `depth` levels below the signal it
+/// was sent to. The receivers of a signal at the last level are not updated,
+/// and a `DepthError` naming that signal is reported, see `set_depth_hook`.
+/// This is a safety net against runaway chains, such as ones built by mistake
+/// in a loop. The same limit applies to the rounds of updates that effects send while an
+/// update settles, so an effect that keeps feeding back into its own source
+/// stops too.
+/// The limit applies to all threads and defaults to `DEFAULT_MAX_DEPTH`.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{api::{self, SignalTrait}, signal, Signal};
+///
+/// api::set_max_depth(2);
+/// let a = signal!(1);
+/// let b = signal!([a] a + 1);
+/// let c = signal!([b] b + 1);
+/// let d = signal!([c] c + 1);
+///
+/// a.send(10);
+/// assert_eq!(c.get(), 12);
+/// assert_eq!(d.get(), 4);
+/// # api::set_max_depth(api::DEFAULT_MAX_DEPTH);
+/// ```
+pub fn set_max_depth(depth: usize) {
+    MAX_DEPTH.store(depth, atomic::Ordering::Relaxed);
+}
 
-    /// Access the dirty counter.
-    fn dirty(&self) -> &Self::Rc<Self::Ptr<usize>>;
+/// Returns the maximum depth of propagation, see `set_max_depth`.
+pub fn max_depth() -> usize {
+    MAX_DEPTH.load(atomic::Ordering::Relaxed)
 }
 
-/// Public interface for reactive signals.
+/// Function called with each update cut short by `set_max_depth`.
+type DepthHook = Box<dyn Fn(&DepthError) + Send + Sync>;
+
+#[cfg(feature = "std")]
+static DEPTH_HOOK: RwLock<Option<DepthHook>> = RwLock::new(None);
+#[cfg(not(feature = "std"))]
+static DEPTH_HOOK: Global<RefCell<Option<DepthHook>>> = Global(RefCell::new(None));
+
+/// Sets the function called when an update is cut short by the limit of
+/// `set_max_depth`.
 ///
-/// Signals are reactive values that can:
-/// - Hold a value that can be read with `get()` or `borrow()`
-/// - Be updated with new values via `send()`
-/// - Depend on other signals and react to their changes
-/// - Have other signals depend on them through receivers
-#[allow(private_bounds)]
-pub trait SignalTrait: SealedSignalTrait {
-    #[allow(private_interfaces)]
-    fn new(value: Self::Inner) -> Self
-    where
-        Self: Sized,
-        Self::Inner: Sized,
-    {
-        Self::init(
-            Self::Rc::<Self::Ptr<Self::Inner>>::new(Self::Ptr::<Self::Inner>::new(value)),
-            None,
-            None,
-            Self::Rc::<Self::Ptr<Vec<Box<Self::Receiver>>>>::new(Self::Ptr::<
-                Vec<Box<Self::Receiver>>,
-            >::new(Vec::new())),
-            Self::Rc::<Self::Ptr<usize>>::new(Self::Ptr::<usize>::new(0)),
-        )
-    }
-    #[allow(private_interfaces)]
-    fn get(&self) -> Self::Inner
-    where
-        Self::Inner: Clone,
-    {
-        self.inner().borrow().clone()
-    }
-    #[allow(private_interfaces)]
-    fn borrow(&self) -> <Self::Ptr<Self::Inner> as InternalMutable>::Ref<'_> {
-        self.inner().borrow()
-    }
-    #[allow(private_interfaces)]
-    fn borrow_mut(&self) -> <Self::Ptr<Self::Inner> as InternalMutable>::RefMut<'_> {
-        self.inner().borrow_mut()
-    }
-    #[allow(private_interfaces)]
-    /// Registers a dependent signal that will react when this signal changes.
-    ///
-    /// This method accepts anything that can be converted into `Box<Self::Receiver>`,
-    /// so you can pass signal instances directly without manually boxing them.
-    ///
-    /// # Example
-    /// ```
-    /// let count = signal!(0);
-    /// let doubled = signal!([count] count * 2);
-    ///
-    /// // Add doubled as a receiver of count
-    /// // doubled will update automatically when count changes
-    /// count.add_receiver(doubled);
-    /// ```
-    fn add_receiver(&self, receiver: impl Into<Box<Self::Receiver>>) {
-        self.receivers().borrow_mut().push(receiver.into());
+/// Without a hook, the error is printed to standard error if `std` is
+/// enabled. A hook can log it elsewhere, count it, or ignore it.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use std::sync::{Arc, Mutex};
+/// use reactivity::{api::{self, DepthError, SignalTrait}, signal, Signal};
+///
+/// let errors = Arc::new(Mutex::new(Vec::new()));
+/// let errors_ = errors.clone();
+/// api::set_depth_hook(move |error| errors_.lock().unwrap().push(error.clone()));
+///
+/// api::set_max_depth(1);
+/// let a = signal!(1);
+/// let b = signal!([a] a + 1);
+/// let _c = signal!([b] b + 1);
+/// a.send(2);
+/// api::set_max_depth(api::DEFAULT_MAX_DEPTH);
+///
+/// assert!(matches!(errors.lock().unwrap()[0], DepthError::Signals { depth: 1, .. }));
+/// ```
+pub fn set_depth_hook(hook: impl Fn(&DepthError) + Send + Sync + 'static) {
+    *DEPTH_HOOK.write() = Some(Box::new(hook));
+}
+
+fn report_depth(error: DepthError) {
+    match &*DEPTH_HOOK.read() {
+        Some(hook) => hook(&error),
+        None => eprintln!("error: {error}"),
     }
+}
 
-    #[allow(private_interfaces)]
-    fn send(&self, value: Self::Inner) -> Vec<UpdatePromise>
-    where
-        Self: Sized,
-    {
-        *self.inner().borrow_mut() = value;
-        let receivers = self.receivers().borrow();
+/// Returns the number of dependency updates the signal with the given id
+/// waits for on this thread.
+fn pending(id: usize) -> usize {
+    DIRTY.with(|dirty| dirty.borrow().get(&id).copied().unwrap_or(0))
+}
 
-        receivers
-            .iter()
-            .flat_map(|receiver| receiver.promise())
-            .collect::<Vec<_>>()
+/// Records that a dependency of the signal with the given id has changed.
+fn mark_stale(id: usize) {
+    STALE.with(|stale| stale.borrow_mut().insert(id));
+}
+
+/// Clears the stale mark of the signal with the given id, returning whether
+/// it was set.
+fn take_stale(id: usize) -> bool {
+    STALE.with(|stale| stale.borrow_mut().remove(&id))
+}
+
+/// Drops the pending update of `signal` and of every receiver it marked,
+/// clearing their dirty counters without reacting.
+fn abandon(signal: &dyn Receptive) {
+    let mut pending = vec![signal.clone_boxed()];
+    while let Some(signal) = pending.pop() {
+        signal.reset();
+        take_stale(signal.id());
+        let marked = MARKED.with(|marked| marked.borrow_mut().remove(&signal.id()));
+        pending.extend(marked.into_iter().flatten());
     }
 }
 
-impl<T: SealedSignalTrait> SignalTrait for T {}
+/// A guard that defers propagation until it is dropped.
+///
+/// While a `Batch` is alive, `send` and `update` store values and mark
+/// receivers dirty but withhold notification. Dropping the outermost guard on
+/// the thread flushes all withheld updates in one pass, so a signal depending
+/// on several updated sources reacts only once. Nested guards coalesce into
+/// the outermost one.
+///
+/// Batches are per thread: updates sent from other threads propagate as usual.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{api::SignalTrait, signal, Batch, Signal};
+///
+/// let a = signal!(1);
+/// let b = signal!(2);
+/// let sum = signal!([a, b] a + b);
+///
+/// let batch = Batch::new();
+/// a.send(10);
+/// b.send(20);
+/// assert_eq!(sum.get(), 3);
+///
+/// drop(batch);
+/// assert_eq!(sum.get(), 30);
+/// ```
+pub struct Batch {
+    // Batches are tied to the thread they were opened on.
+    _not_send: PhantomData<*const ()>,
+}
 
-impl<T: SignalTrait + 'static> From<T> for Box<dyn Receptive> {
-    fn from(signal: T) -> Self {
-        Box::new(signal)
+impl Batch {
+    /// Opens a batch on the current thread.
+    pub fn new() -> Self {
+        BATCH_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Self {
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Returns whether a batch is open on the current thread.
+    pub fn is_active() -> bool {
+        BATCH_DEPTH.with(|depth| depth.get() > 0)
+    }
+
+    /// Withholds the promises until the outermost batch closes.
+    fn defer(promises: Vec<UpdatePromise>) {
+        BATCHED.with(|batched| batched.borrow_mut().extend(promises));
     }
 }
 
-impl<T: SignalTrait + Send + Sync + 'static> From<T> for Box<dyn Receptive + Send + Sync> {
+impl Default for Batch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Batch {
+    fn drop(&mut self) {
+        let depth = BATCH_DEPTH.with(|depth| {
+            depth.set(depth.get() - 1);
+            depth.get()
+        });
+        if depth == 0 {
+            // Resolving runs effects, which may open batches of their own.
+            let promises = BATCHED.with(|batched| mem::take(&mut *batched.borrow_mut()));
+            settle(promises);
+        }
+    }
+}
+
+/// Runs `f` inside a `Batch`, propagating all updates it sends at once.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{api::SignalTrait, batch, signal, Signal};
+///
+/// let a = signal!(1);
+/// let b = signal!(2);
+/// let sum = signal!([a, b] a + b);
+///
+/// batch(|| {
+///     a.send(10);
+///     b.send(20);
+/// });
+/// assert_eq!(sum.get(), 30);
+/// ```
+pub fn batch<R>(f: impl FnOnce() -> R) -> R {
+    let _batch = Batch::new();
+    f()
+}
+
+/// Sends each value to its signal and propagates all of them at once.
+///
+/// All values are in place before any receiver reacts, and a receiver of
+/// several of the signals reacts only once, as with `batch`.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{api::SignalTrait, send_all, signal, Signal};
+///
+/// let a = signal!(1);
+/// let b = signal!(2);
+/// let sum = signal!([a, b] a + b);
+///
+/// send_all(&[(a.clone(), 10), (b.clone(), 20)]);
+/// assert_eq!(sum.get(), 30);
+/// assert_eq!(sum.update_count(), 1);
+/// ```
+pub fn send_all<S, T>(updates: &[(S, T)])
+where
+    S: SignalTrait<Inner = T>,
+    T: Clone,
+{
+    batch(|| {
+        for (signal, value) in updates {
+            signal.send(value.clone());
+        }
+    });
+}
+
+/// A signal read by a tracked function, see `Signal::computed`.
+pub(crate) trait Source: Receptive {
+    /// Adds `receiver` as a receiver of this signal.
+    fn add_dependent(&self, receiver: Box<dyn Receptive>);
+}
+
+/// The signals read by a tracked function, each once.
+pub(crate) type Reads = Vec<Box<dyn Source>>;
+
+/// Records a read of `source` by the innermost tracked function, if any.
+pub(crate) fn track(source: &(impl Source + Clone + 'static)) {
+    TRACKING.with(|tracking| {
+        if let Some(Some(reads)) = tracking.borrow_mut().last_mut() {
+            if !reads.iter().any(|read| read.id() == source.id()) {
+                reads.push(Box::new(source.clone()));
+            }
+        }
+    });
+}
+
+/// Removes the frame of a `tracked` or `untrack` call when dropped, also
+/// when unwinding.
+struct Tracking;
+
+impl Drop for Tracking {
+    fn drop(&mut self) {
+        // Released before the reads drop, as dropping a signal may read others.
+        let reads = TRACKING.with(|tracking| tracking.borrow_mut().pop());
+        drop(reads);
+    }
+}
+
+/// Runs `f` and returns its result together with the signals it read.
+pub(crate) fn tracked<R>(f: impl FnOnce() -> R) -> (R, Reads) {
+    TRACKING.with(|tracking| tracking.borrow_mut().push(Some(Vec::new())));
+    let _frame = Tracking;
+    let value = f();
+    let reads = TRACKING.with(|tracking| tracking.borrow_mut().last_mut().unwrap().take());
+    (value, reads.unwrap_or_default())
+}
+
+/// Runs `f` without recording the signals it reads as dependencies.
+///
+/// Inside the function of `Signal::computed`, this reads signals that
+/// should not cause a recompute when they change, such as configuration.
+/// Unlike `peek`, it covers every read in `f`, including those made by
+/// functions it calls. Outside a tracked function it just runs `f`.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{api::SignalTrait, untrack, Signal};
+///
+/// let count = Signal::new(1);
+/// let factor = Signal::new(10);
+/// let (count_, factor_) = (count.clone(), factor.clone());
+/// let scaled = Signal::computed(move || count_.get() * untrack(|| factor_.get()));
+///
+/// factor.send(100);
+/// assert_eq!(scaled.get(), 10);
+/// assert_eq!(factor.receiver_count(), 0);
+///
+/// count.send(2);
+/// assert_eq!(scaled.get(), 200);
+/// ```
+pub fn untrack<R>(f: impl FnOnce() -> R) -> R {
+    TRACKING.with(|tracking| tracking.borrow_mut().push(None));
+    let _frame = Tracking;
+    f()
+}
+
+/// The signals a tracked function read on its last run, see
+/// `Signal::computed`.
+#[derive(Default)]
+pub(crate) struct Dependencies {
+    /// Ids of the signals `signal` was added to as a receiver for a read
+    ids: RefCell<Vec<usize>>,
+}
+
+impl Dependencies {
+    /// Makes `signal` a receiver of exactly the signals in `reads`.
+    ///
+    /// Signals read for the first time get `signal` as a receiver, and the
+    /// ones read on the last run but not on this one lose it. Sources that
+    /// `signal` was added to by other means are left as they are.
+    pub(crate) fn update<S>(&self, signal: &S, reads: Reads)
+    where
+        S: SignalTrait + Clone + 'static,
+    {
+        let reads: Vec<_> = reads
+            .into_iter()
+            .filter(|read| read.id() != signal.id())
+            .collect();
+        let mut ids = self.ids.borrow_mut();
+        let stale: Vec<usize> = ids
+            .iter()
+            .copied()
+            .filter(|id| !reads.iter().any(|read| read.id() == *id))
+            .collect();
+        for id in stale {
+            let removed = remove_by_id(&mut signal.sources().borrow_mut(), id);
+            if let Some(source) = removed {
+                source.remove_dependent(signal.id());
+            }
+        }
+        ids.retain(|id| reads.iter().any(|read| read.id() == *id));
+
+        let known: Vec<usize> = signal
+            .sources()
+            .borrow()
+            .iter()
+            .filter(|source| source.is_alive())
+            .map(|source| source.id())
+            .collect();
+        for read in reads {
+            if !known.contains(&read.id()) {
+                ids.push(read.id());
+                read.add_dependent(signal.clone().into());
+            }
+        }
+    }
+}
+
+/// Removes the first receiver with the given id.
+fn remove_by_id<R: Receptive + ?Sized>(receivers: &mut Vec<Box<R>>, id: usize) -> Option<Box<R>> {
+    let index = receivers.iter().position(|r| r.id() == id)?;
+    Some(receivers.remove(index))
+}
+
+/// Inserts `receiver` after the receivers of `signal` with the same or a
+/// higher priority, keeping the list sorted from the highest priority down.
+fn insert_receiver<S: SealedSignalTrait + ?Sized>(
+    signal: &S,
+    receiver: Box<S::Receiver>,
+    priority: i32,
+) {
+    let mut receivers = signal.receivers().borrow_mut();
+    let mut priorities = signal.priorities().borrow_mut();
+    if !priorities.is_empty() {
+        priorities.retain(|id, _| receivers.iter().any(|receiver| receiver.id() == *id));
+    }
+    let index = receivers.partition_point(|receiver| {
+        priorities.get(&receiver.id()).copied().unwrap_or(0) >= priority
+    });
+    if priority != 0 {
+        priorities.insert(receiver.id(), priority);
+    }
+    receivers.insert(index, receiver);
+}
+
+/// Drops the receivers of `signal` that no longer exist.
+fn prune<S: SealedSignalTrait + ?Sized>(signal: &S) {
+    signal
+        .receivers()
+        .borrow_mut()
+        .retain(|receiver| receiver.is_alive());
+}
+
+/// Values stored by a signal that a listener is yet to see, and whether a
+/// thread is handing them to it, see `Signal::on_value`.
+pub(crate) type Snapshots<T> = (VecDeque<T>, bool);
+
+/// Resets the flag of `Snapshots` if the listener panics.
+struct Delivering<'a, P: InternalMutable<Inner = Snapshots<T>>, T>(&'a P);
+
+impl<P: InternalMutable<Inner = Snapshots<T>>, T> Drop for Delivering<'_, P, T> {
+    fn drop(&mut self) {
+        self.0.borrow_mut().1 = false;
+    }
+}
+
+/// Passes the values in `snapshots` to `f` one at a time, oldest first.
+///
+/// Only one thread does so at a time. Another one finding the flag set
+/// leaves its values to that thread, which checks for more under the same
+/// lock that clears the flag, so none are left behind. The lock is released
+/// while `f` runs, and values stored in the meantime are passed on next.
+pub(crate) fn deliver<P, T>(snapshots: &P, f: impl Fn(&T))
+where
+    P: InternalMutable<Inner = Snapshots<T>>,
+{
+    if mem::replace(&mut snapshots.borrow_mut().1, true) {
+        return;
+    }
+    loop {
+        let mut pending = snapshots.borrow_mut();
+        let Some(value) = pending.0.pop_front() else {
+            pending.1 = false;
+            return;
+        };
+        drop(pending);
+        let delivering = Delivering(snapshots);
+        f(&value);
+        mem::forget(delivering);
+    }
+}
+
+/// A handle to a registered receiver that unregisters it when dropped.
+///
+/// Returned by `SignalTrait::subscribe`. Use the `Subscription` aliases in
+/// `reactivity` and `reactivity::sync` to name it.
+#[must_use = "dropping a `Subscription` unregisters the receiver immediately"]
+pub struct Subscription<R: Receptive + ?Sized> {
+    /// Weak handle to the signal the receiver was registered with
+    source: Option<Box<R>>,
+    id: usize,
+}
+
+impl<R: Receptive + ?Sized> Subscription<R> {
+    /// Unregisters the receiver now.
+    pub fn unsubscribe(self) {}
+
+    /// Keeps the receiver registered for as long as the source lives.
+    pub fn forget(mut self) {
+        self.source = None;
+    }
+}
+
+impl<R: Receptive + ?Sized> Drop for Subscription<R> {
+    fn drop(&mut self) {
+        if let Some(source) = self.source.take() {
+            source.remove_dependent(self.id);
+        }
+    }
+}
+
+/// A read-only view of a signal.
+///
+/// Created by `SignalTrait::read_only`. The view shares its state with the
+/// signal, so updates sent by the owner reach receivers added through the view,
+/// but the view itself has no way to send or mutate the value. Use the
+/// `ReadSignal` aliases in `reactivity` and `reactivity::sync` to name it.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{api::SignalTrait, signal, ReadSignal, Signal};
+///
+/// fn watch(count: ReadSignal<i32>) -> Signal<i32> {
+///     signal!([count] count * 2)
+/// }
+///
+/// let count = signal!(1);
+/// let doubled = watch(count.read_only());
+///
+/// count.send(4);
+/// assert_eq!(doubled.get(), 8);
+/// ```
+pub struct ReadSignal<S> {
+    pub(crate) signal: S,
+}
+
+impl<S: Clone> Clone for ReadSignal<S> {
+    fn clone(&self) -> Self {
+        Self {
+            signal: self.signal.clone(),
+        }
+    }
+}
+
+impl<S: SignalTrait> ReadSignal<S> {
+    /// Returns a clone of the current value, see `SignalTrait::get`.
+    pub fn get(&self) -> S::Inner
+    where
+        S::Inner: Clone,
+    {
+        self.signal.get()
+    }
+
+    /// Calls `f` with a reference to the current value, see `SignalTrait::with`.
+    pub fn with<R>(&self, f: impl FnOnce(&S::Inner) -> R) -> R {
+        self.signal.with(f)
+    }
+
+    /// Borrows the current value, see `SignalTrait::borrow`.
+    pub fn borrow(&self) -> <S::Ptr<S::Inner> as InternalMutable>::Ref<'_> {
+        self.signal.borrow()
+    }
+
+    /// Borrows the current value unless it is mutably borrowed, see
+    /// `SignalTrait::try_borrow`.
+    pub fn try_borrow(
+        &self,
+    ) -> Result<<S::Ptr<S::Inner> as InternalMutable>::Ref<'_>, BorrowError> {
+        self.signal.try_borrow()
+    }
+
+    /// Registers a dependent signal, see `SignalTrait::add_receiver`.
+    pub fn add_receiver(&self, receiver: impl Into<Box<S::Receiver>>)
+    where
+        S: Clone + Into<Box<S::Receiver>>,
+    {
+        self.signal.add_receiver(receiver)
+    }
+
+    /// Registers a dependent signal with a priority, see
+    /// `SignalTrait::add_receiver_with_priority`.
+    pub fn add_receiver_with_priority(&self, receiver: impl Into<Box<S::Receiver>>, priority: i32)
+    where
+        S: Clone + Into<Box<S::Receiver>>,
+    {
+        self.signal.add_receiver_with_priority(receiver, priority)
+    }
+
+    /// Unregisters a receiver, see `SignalTrait::remove_receiver`.
+    pub fn remove_receiver(&self, receiver: &impl Receptive) -> bool {
+        self.signal.remove_receiver(receiver)
+    }
+
+    /// Registers a dependent signal until the returned handle is dropped, see
+    /// `SignalTrait::subscribe`.
+    pub fn subscribe(&self, receiver: impl Into<Box<S::Receiver>>) -> Subscription<S::Receiver>
+    where
+        S: Clone + Into<Box<S::Receiver>>,
+    {
+        self.signal.subscribe(receiver)
+    }
+
+    /// Returns the number of receivers that still exist.
+    pub fn receiver_count(&self) -> usize {
+        self.signal.receiver_count()
+    }
+}
+
+/// A write-only handle to a signal.
+///
+/// Created together with a `ReadSignal` by `SignalTrait::split`. Both halves
+/// share the same state, so values sent through this handle reach the
+/// receivers of the read half, and either half keeps working after the other
+/// is dropped. Use the `WriteSignal` aliases in `reactivity` and
+/// `reactivity::sync` to name it.
+pub struct WriteSignal<S> {
+    signal: S,
+}
+
+impl<S: Clone> Clone for WriteSignal<S> {
+    fn clone(&self) -> Self {
+        Self {
+            signal: self.signal.clone(),
+        }
+    }
+}
+
+impl<S: SignalTrait> WriteSignal<S> {
+    /// Sends a new value, see `SignalTrait::send`.
+    pub fn send(&self, value: S::Inner) -> Vec<UpdatePromise> {
+        self.signal.send(value)
+    }
+
+    /// Sends a value only if it differs from the current one, see
+    /// `SignalTrait::set_if_changed`.
+    pub fn set_if_changed(&self, value: S::Inner) -> bool
+    where
+        S::Inner: PartialEq,
+    {
+        self.signal.set_if_changed(value)
+    }
+
+    /// Mutates the value in place, see `SignalTrait::update`.
+    pub fn update(&self, f: impl FnOnce(&mut S::Inner)) -> Vec<UpdatePromise> {
+        self.signal.update(f)
+    }
+
+    /// Stores a new value and returns the previous one, see
+    /// `SignalTrait::replace`.
+    pub fn replace(&self, value: S::Inner) -> S::Inner {
+        self.signal.replace(value)
+    }
+
+    /// Resets the value to its default and returns the previous one, see
+    /// `SignalTrait::take`.
+    pub fn take(&self) -> S::Inner
+    where
+        S::Inner: Default,
+    {
+        self.signal.take()
+    }
+}
+
+/// A handle to an effect created with `Signal::effect` or the `effect!` macro.
+///
+/// The handle owns the signal the effect runs in. Its sources only hold weak
+/// references to it, so dropping the handle detaches the effect. Use the
+/// `EffectHandle` aliases in `reactivity` and `reactivity::sync` to name it.
+#[must_use = "dropping an `EffectHandle` detaches the effect immediately"]
+pub struct EffectHandle<S> {
+    observer: S,
+}
+
+impl<S> EffectHandle<S> {
+    pub(crate) fn new(observer: S) -> Self {
+        Self { observer }
+    }
+
+    /// Returns the signal the effect runs in.
+    ///
+    /// Add it as a receiver of each signal the effect depends on.
+    pub fn observer(&self) -> &S {
+        &self.observer
+    }
+
+    /// Detaches the effect now.
+    pub fn detach(self) {}
+}
+
+/// A receiver that runs a function each time one of its sources updates.
+///
+/// Unlike the signal of an `EffectHandle`, a `FnReceiver` is moved into the
+/// receiver list of the signal it is added to, which owns it from then on, so
+/// it runs for as long as that signal lives or until it is removed. Adding it
+/// to several signals takes a clone for each. Build one with the `FnReceiver`
+/// aliases in `reactivity` and `reactivity::sync`.
+pub struct FnReceiver<S> {
+    observer: S,
+}
+
+impl<S> FnReceiver<S> {
+    pub(crate) fn from_observer(observer: S) -> Self {
+        Self { observer }
+    }
+}
+
+impl<S: Clone> Clone for FnReceiver<S> {
+    fn clone(&self) -> Self {
+        Self {
+            observer: self.observer.clone(),
+        }
+    }
+}
+
+impl<S: Receptive> Dirty for FnReceiver<S> {
+    fn count(&self) -> usize {
+        self.observer.count()
+    }
+
+    fn increase(&self) {
+        self.observer.increase();
+    }
+
+    fn decrease(&self) {
+        self.observer.decrease();
+    }
+
+    fn reset(&self) {
+        self.observer.reset();
+    }
+}
+
+impl<S: Receptive> Receptive for FnReceiver<S> {
+    fn react(&self) -> Vec<UpdatePromise> {
+        self.observer.react()
+    }
+
+    fn enter_mark(&self) -> Option<Vec<Box<dyn Receptive>>> {
+        self.observer.enter_mark()
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Receptive> {
+        self.observer.clone_boxed()
+    }
+
+    fn id(&self) -> usize {
+        self.observer.id()
+    }
+
+    fn sequence(&self) -> usize {
+        self.observer.sequence()
+    }
+
+    fn label(&self) -> String {
+        self.observer.label()
+    }
+
+    fn dependents(&self) -> Vec<Box<dyn Receptive>> {
+        self.observer.dependents()
+    }
+
+    fn remove_dependent(&self, id: usize) -> bool {
+        self.observer.remove_dependent(id)
+    }
+
+    fn add_source(&self, source: &mut dyn any::Any) {
+        self.observer.add_source(source);
+    }
+}
+
+impl<S: Receptive + 'static> From<FnReceiver<S>> for Box<dyn Receptive> {
+    fn from(receiver: FnReceiver<S>) -> Self {
+        Box::new(receiver)
+    }
+}
+
+impl<S: Receptive + Send + Sync + 'static> From<FnReceiver<S>>
+    for Box<dyn Receptive + Send + Sync>
+{
+    fn from(receiver: FnReceiver<S>) -> Self {
+        Box::new(receiver)
+    }
+}
+
+/// A collection of subscriptions and effects that all detach when it is
+/// dropped.
+///
+/// A component with many reactions can keep them in one scope rather than
+/// holding each handle in a field of its own. Besides `add` and `add_effect`,
+/// the `ObserverScope` aliases in `reactivity` and `reactivity::sync` have a
+/// `watch` method that registers the watcher and keeps its handle.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use std::{cell::Cell, rc::Rc};
+/// use reactivity::{api::SignalTrait, ObserverScope, Signal};
+///
+/// let count = Signal::new(0);
+/// let seen = Rc::new(Cell::new(0));
+/// let mut scope = ObserverScope::new();
+/// let seen_ = seen.clone();
+/// scope.watch(&count, move |_, new| seen_.set(*new));
+/// scope.add(count.on_change(|| println!("count changed")));
+///
+/// count.send(1);
+/// drop(scope);
+/// count.send(2);
+/// assert_eq!(seen.get(), 1);
+/// assert_eq!(count.receiver_count(), 0);
+/// ```
+#[must_use = "dropping an `ObserverScope` detaches everything it holds"]
+pub struct ObserverScope<S: SealedSignalTrait> {
+    subscriptions: Vec<Subscription<S::Receiver>>,
+    effects: Vec<EffectHandle<S>>,
+}
+
+impl<S: SealedSignalTrait> ObserverScope<S> {
+    /// Creates an empty scope.
+    pub fn new() -> Self {
+        Self {
+            subscriptions: Vec::new(),
+            effects: Vec::new(),
+        }
+    }
+
+    /// Keeps `subscription` until the scope is dropped or cleared.
+    pub fn add(&mut self, subscription: Subscription<S::Receiver>) {
+        self.subscriptions.push(subscription);
+    }
+
+    /// Keeps `effect` until the scope is dropped or cleared.
+    pub fn add_effect(&mut self, effect: EffectHandle<S>) {
+        self.effects.push(effect);
+    }
+
+    /// Returns the number of subscriptions and effects held.
+    pub fn len(&self) -> usize {
+        self.subscriptions.len() + self.effects.len()
+    }
+
+    /// Returns whether the scope holds nothing.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Detaches everything held now, leaving the scope empty for reuse.
+    pub fn clear(&mut self) {
+        self.subscriptions.clear();
+        self.effects.clear();
+    }
+}
+
+impl<S: SealedSignalTrait> Default for ObserverScope<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A trait for tracking the dirty state of reactive components.
+///
+/// Components with a dirty count > 0 are pending updates.
+pub(crate) trait Dirty {
+    /// Gets the current dirty counter value.
+    fn count(&self) -> usize;
+    /// Increases the dirty counter.
+    fn increase(&self);
+    /// Decreases the dirty counter, clamping at zero.
+    fn decrease(&self);
+    /// Clears the dirty counter.
+    fn reset(&self);
+}
+
+impl<T> Dirty for T
+where
+    T: Receptive + SealedSignalTrait + 'static,
+{
+    fn count(&self) -> usize {
+        pending(self.id())
+    }
+
+    fn increase(&self) {
+        DIRTY.with(|dirty| *dirty.borrow_mut().entry(self.id()).or_insert(0) += 1);
+    }
+
+    fn decrease(&self) {
+        let id = self.id();
+        let dirty = DIRTY.with(|dirty| {
+            let mut dirty = dirty.borrow_mut();
+            let count = dirty.remove(&id).unwrap_or(0);
+            if count > 1 {
+                dirty.insert(id, count - 1);
+            }
+            count
+        });
+        debug_assert!(dirty > 0, "dirty counter of `{}` underflowed", self.label());
+    }
+
+    fn reset(&self) {
+        DIRTY.with(|dirty| dirty.borrow_mut().remove(&self.id()));
+    }
+}
+
+/// Core trait for reactive components.
+///
+/// Objects implementing this trait can react to changes and trigger reactions
+/// in dependent components.
+///
+/// Only the signal types and `FnReceiver` implement it. Its methods keep the
+/// count of the updates each receiver waits for and are not a stable
+/// interface, so the trait cannot be implemented outside this crate. To run
+/// code of your own when a signal changes, add a `FnReceiver` to it, or see
+/// `EffectHandle` for an effect depending on several signals. Receivers are
+/// not given the new value, use `Signal::on_value` to have it passed along.
+#[allow(private_bounds)]
+pub trait Receptive: Dirty {
+    /// Update the signal and trigger its reaction.
+    ///
+    /// Called once all pending dependencies have resolved. Returns the promises
+    /// of the receivers marked by `mark`.
+    fn react(&self) -> Vec<UpdatePromise>;
+
+    /// Mark this signal as waiting for one more dependency.
+    ///
+    /// The first mark of an update also marks every receiver, so each signal
+    /// waits for all of its pending dependencies before reacting.
+    fn mark(&self) {
+        mark_graph(self.clone_boxed());
+    }
+
+    /// Count one more pending dependency without marking any receivers.
+    ///
+    /// Returns the receivers left to mark if this is the first mark of the
+    /// update, see `mark`.
+    fn enter_mark(&self) -> Option<Vec<Box<dyn Receptive>>>;
+
+    /// Mark this signal and create the promise that resolves the mark.
+    ///
+    /// Called when a dependency changes to schedule updates.
+    fn promise(&self) -> UpdatePromise {
+        self.mark();
+        UpdatePromise(self.clone_boxed())
+    }
+
+    /// Clone this object as a trait object.
+    ///
+    /// Weak handles are upgraded, so the clone keeps a live signal alive.
+    fn clone_boxed(&self) -> Box<dyn Receptive>;
+
+    /// Identifier of the underlying shared state.
+    ///
+    /// Clones of the same signal share an id, which is what receivers are
+    /// matched by when they are removed.
+    fn id(&self) -> usize;
+
+    /// Position of the underlying signal in the order of creation.
+    ///
+    /// Receivers are sorted by it in deterministic mode, see
+    /// `set_deterministic`.
+    fn sequence(&self) -> usize;
+
+    /// Human readable label used in diagnostics.
+    ///
+    /// This is the name given with `SignalTrait::named`, or the value type and
+    /// id of unnamed signals.
+    fn label(&self) -> String;
+
+    /// Returns whether the underlying signal still exists.
+    ///
+    /// Only weak handles can outlive their signal.
+    fn is_alive(&self) -> bool {
+        true
+    }
+
+    /// Clones of the live receivers currently registered with this object.
+    fn dependents(&self) -> Vec<Box<dyn Receptive>>;
+
+    /// Removes one registered receiver with the given id.
+    ///
+    /// Returns whether a receiver was removed.
+    fn remove_dependent(&self, id: usize) -> bool;
+
+    /// Records a signal this object was registered with as a receiver.
+    ///
+    /// `source` is an `Option` holding a weak handle boxed as the
+    /// `SealedSignalTrait::Receiver` of this signal's type, which is taken to
+    /// detach the signal once it is dropped. It is passed by reference rather
+    /// than boxed once more, so adding a receiver allocates no more than the
+    /// handles it stores. Sources of another signal type are not recorded.
+    fn add_source(&self, source: &mut dyn any::Any);
+}
+
+impl<T> Receptive for T
+where
+    T: SealedSignalTrait + 'static,
+{
+    fn react(&self) -> Vec<UpdatePromise> {
+        let marked = MARKED.with(|marked| marked.borrow_mut().remove(&self.id()));
+        // Held as promises from the start, so the receivers are reset rather
+        // than left waiting if the processor or the effect panics.
+        let promises: Vec<_> = marked.into_iter().flatten().map(UpdatePromise).collect();
+
+        // Signals whose dependencies all kept their values have nothing to
+        // recompute. Their receivers are resolved without being marked stale,
+        // so they settle the same way.
+        if take_stale(self.id()) {
+            let value = (self.processor().unwrap())(&self.inner().borrow());
+            trace!(
+                signal = %self.label(),
+                changed = value.is_some(),
+                receivers = promises.len(),
+                "signal reacting"
+            );
+            if let Some(value) = value {
+                (self.effect().unwrap())(self, &value);
+                let mut inner = self.inner().borrow_mut();
+                let old = mem::replace(&mut *inner, value);
+                self.stored(&inner);
+                drop(inner);
+                drop(old);
+                *self.updates().borrow_mut() += 1;
+                promises
+                    .iter()
+                    .for_each(|promise| mark_stale(promise.0.id()));
+            }
+        }
+        promises
+    }
+
+    fn enter_mark(&self) -> Option<Vec<Box<dyn Receptive>>> {
+        self.increase();
+        if self.count() > 1 {
+            return None;
+        }
+        // A paused signal still recomputes, but its receivers wait for
+        // `resume`.
+        let mut paused = self.paused().borrow_mut();
+        if paused.0 > 0 {
+            paused.1 = true;
+            return Some(Vec::new());
+        }
+        drop(paused);
+        prune(self);
+        Some(self.dependents())
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Receptive> {
+        Box::new(Self::from_state(self.state().clone()))
+    }
+
+    fn id(&self) -> usize {
+        &**self.state() as *const State<T> as *const () as usize
+    }
+
+    fn sequence(&self) -> usize {
+        self.state().sequence
+    }
+
+    fn label(&self) -> String {
+        match *self.name().borrow() {
+            Some(name) => name.to_string(),
+            None => format!("{}@{:#x}", any::type_name::<T>(), self.id()),
+        }
+    }
+
+    fn dependents(&self) -> Vec<Box<dyn Receptive>> {
+        let mut dependents: Vec<_> = self
+            .receivers()
+            .borrow()
+            .iter()
+            .filter(|receiver| receiver.is_alive())
+            .map(|receiver| receiver.clone_boxed())
+            .collect();
+        if is_deterministic() {
+            // The list is already ordered by priority, which the stable sort
+            // keeps.
+            let priorities = self.priorities().borrow();
+            let priority = |id| priorities.get(&id).copied().unwrap_or(0);
+            dependents.sort_by_key(|receiver| {
+                (
+                    core::cmp::Reverse(priority(receiver.id())),
+                    receiver.sequence(),
+                )
+            });
+        }
+        dependents
+    }
+
+    fn remove_dependent(&self, id: usize) -> bool {
+        // The removed receiver is dropped once the list is released.
+        let removed = remove_by_id(&mut self.receivers().borrow_mut(), id);
+        removed.is_some()
+    }
+
+    fn add_source(&self, source: &mut dyn any::Any) {
+        self.mark_used();
+        let source = source.downcast_mut::<Option<Box<T::Receiver>>>();
+        if let Some(source) = source.and_then(Option::take) {
+            let mut sources = self.sources().borrow_mut();
+            sources.retain(|source| source.is_alive());
+            sources.push(source);
+        }
+    }
+}
+
+/// The state shared by all clones of a signal.
+#[doc(hidden)]
+pub struct State<S: SealedSignalTrait + ?Sized> {
+    /// The current value of the signal
+    inner: S::Ptr<S::Inner>,
+    /// Optional effect function called when the signal is updated
+    effect: Option<Box<S::Effect>>,
+    /// Optional function that computes the signal's value
+    processor: Option<Box<S::Processor>>,
+    /// Weak handles to the receivers that depend on this signal
+    receivers: S::Ptr<Vec<Box<S::Receiver>>>,
+    /// Priorities of the receivers added with one other than zero, by id, see
+    /// `SignalTrait::add_receiver_with_priority`
+    priorities: S::Ptr<Map<usize, i32>>,
+    /// Weak handles to the signals this one was added to as a receiver
+    sources: S::Ptr<Vec<Box<S::Receiver>>>,
+    /// Number of values taken, see `SignalTrait::update_count`
+    updates: S::Ptr<usize>,
+    /// Name shown in diagnostics, set with `SignalTrait::named`
+    name: S::Ptr<Option<&'static str>>,
+    /// Message of the last panic caught while recomputing, see
+    /// `sync::Signal::driven_catching`
+    error: S::Ptr<Option<String>>,
+    /// Functions run when the last handle is dropped, see `Signal::on_drop`
+    cleanups: S::Ptr<Vec<Box<S::Cleanup>>>,
+    /// Functions called with each value stored, see `SealedSignalTrait::stored`
+    hooks: S::Ptr<Vec<Box<S::Hook>>>,
+    /// Number of pauses not yet resumed, and whether an update was held back
+    /// in the meantime, see `SignalTrait::pause`
+    paused: S::Ptr<(usize, bool)>,
+    /// Whether the value is yet to be computed, see `Signal::driven_lazy`
+    lazy: S::Ptr<bool>,
+    /// Whether the value never changes, see `SignalTrait::constant`
+    constant: bool,
+    /// Position of the signal in the order of creation, see
+    /// `set_deterministic`
+    sequence: usize,
+    /// Whether the signal was read or added as a receiver, see
+    /// `set_unused_hook`
+    #[cfg(debug_assertions)]
+    used: AtomicBool,
+}
+
+impl<S: SealedSignalTrait + ?Sized> State<S> {
+    fn new(
+        inner: S::Inner,
+        effect: Option<Box<S::Effect>>,
+        processor: Option<Box<S::Processor>>,
+        constant: bool,
+    ) -> Self
+    where
+        S::Inner: Sized,
+    {
+        State {
+            inner: S::Ptr::<S::Inner>::new(inner),
+            effect,
+            processor,
+            receivers: S::Ptr::<Vec<Box<S::Receiver>>>::new(Vec::new()),
+            priorities: S::Ptr::<Map<usize, i32>>::new(Map::new()),
+            sources: S::Ptr::<Vec<Box<S::Receiver>>>::new(Vec::new()),
+            updates: S::Ptr::<usize>::new(0),
+            name: S::Ptr::<Option<&'static str>>::new(None),
+            error: S::Ptr::<Option<String>>::new(None),
+            cleanups: S::Ptr::<Vec<Box<S::Cleanup>>>::new(Vec::new()),
+            hooks: S::Ptr::<Vec<Box<S::Hook>>>::new(Vec::new()),
+            paused: S::Ptr::<(usize, bool)>::new((0, false)),
+            lazy: S::Ptr::<bool>::new(false),
+            constant,
+            sequence: SEQUENCE.fetch_add(1, atomic::Ordering::Relaxed),
+            #[cfg(debug_assertions)]
+            used: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<S: SealedSignalTrait + ?Sized> Drop for State<S> {
+    fn drop(&mut self) {
+        // Runs once the last handle is gone, so the signal detaches from its
+        // sources instead of waiting for them to prune it.
+        let id = self as *const Self as *const () as usize;
+        let sources = mem::take(&mut *self.sources.borrow_mut());
+        for source in sources {
+            while source.remove_dependent(id) {}
+        }
+        for cleanup in mem::take(&mut *self.cleanups.borrow_mut()) {
+            cleanup();
+        }
+        #[cfg(debug_assertions)]
+        if self.processor.is_some() && !self.used.load(Ordering::Relaxed) {
+            let label = match *self.name.borrow() {
+                Some(name) => name.to_string(),
+                None => format!("{}@{:#x}", any::type_name::<S>(), id),
+            };
+            report_unused(&label);
+        }
+        // The functions may own the last handles to other signals, whose
+        // functions own the next ones. Dropping them here would nest once per
+        // signal of a chain, so the outermost drop frees them in a loop.
+        let functions = (self.processor.take(), self.effect.take());
+        if COLLECTING.with(|collecting| collecting.replace(true)) {
+            GARBAGE.with(|garbage| garbage.borrow_mut().push(Box::new(functions)));
+            return;
+        }
+        let _collecting = Collecting;
+        drop(functions);
+        while let Some(functions) = GARBAGE.with(|garbage| garbage.borrow_mut().pop()) {
+            drop(functions);
+        }
+    }
+}
+
+/// Clears `COLLECTING` when dropped, also when unwinding.
+struct Collecting;
+
+impl Drop for Collecting {
+    fn drop(&mut self) {
+        COLLECTING.with(|collecting| collecting.set(false));
+    }
+}
+
+/// Function called with the label of each derived signal dropped unused.
+type UnusedHook = Box<dyn Fn(&str) + Send + Sync>;
+
+#[cfg(feature = "std")]
+static UNUSED_HOOK: RwLock<Option<UnusedHook>> = RwLock::new(None);
+#[cfg(not(feature = "std"))]
+static UNUSED_HOOK: Global<RefCell<Option<UnusedHook>>> = Global(RefCell::new(None));
+
+/// Sets the function called when a derived signal is dropped unused.
+///
+/// A derived signal that was never read and never added as a receiver has
+/// done nothing but compute its initial value, which usually means it was
+/// not wired up. In debug builds, dropping such a signal calls `hook` with
+/// its label, see `SignalTrait::named`. Without a hook, a warning is printed
+/// to standard error if `std` is enabled. Release builds skip the check
+/// entirely.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use std::sync::{Arc, Mutex};
+/// use reactivity::{api::{self, SignalTrait}, Signal};
+///
+/// let unused = Arc::new(Mutex::new(Vec::new()));
+/// let unused_ = unused.clone();
+/// api::set_unused_hook(move |label| unused_.lock().unwrap().push(label.to_string()));
+///
+/// let count = Signal::new(1);
+/// let source = count.clone();
+/// // Never added as a receiver of `count`
+/// let doubled = Signal::driven(move || source.get() * 2, |_, _| {}).named("doubled");
+/// drop(doubled);
+/// # #[cfg(debug_assertions)]
+/// assert!(unused.lock().unwrap().contains(&"doubled".to_string()));
+/// ```
+pub fn set_unused_hook(hook: impl Fn(&str) + Send + Sync + 'static) {
+    *UNUSED_HOOK.write() = Some(Box::new(hook));
+}
+
+#[cfg(debug_assertions)]
+fn report_unused(label: &str) {
+    match &*UNUSED_HOOK.read() {
+        Some(hook) => hook(label),
+        None => eprintln!(
+            "warning: derived signal `{label}` was dropped without being read or added as a receiver"
+        ),
+    }
+}
+
+/// A handle to a signal that does not keep it alive.
+///
+/// Signals hold their receivers through weak handles, so a derived signal is
+/// freed once its last strong handle is dropped, even while its sources live.
+/// A dropped receiver is skipped when its sources notify.
+///
+/// # Example
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{api::SignalTrait, signal, Signal};
+///
+/// let count = signal!(1);
+/// let doubled = signal!([count] count * 2);
+/// let weak = doubled.downgrade();
+/// assert!(weak.upgrade().is_some());
+///
+/// drop(doubled);
+/// assert!(weak.upgrade().is_none());
+/// count.send(2);
+/// ```
+pub struct WeakSignal<S: SealedSignalTrait> {
+    state: <S::Rc<State<S>> as Shared>::Weak,
+    /// Id of the signal, kept so it is still known after the signal is dropped
+    id: usize,
+    /// Position of the signal in the order of creation, kept for the same
+    /// reason
+    sequence: usize,
+}
+
+impl<S: SealedSignalTrait> WeakSignal<S> {
+    /// Returns a strong handle to the signal if it still exists.
+    pub fn upgrade(&self) -> Option<S> {
+        <S::Rc<State<S>> as Shared>::upgrade(&self.state).map(S::from_state)
+    }
+}
+
+impl<S: SealedSignalTrait> Clone for WeakSignal<S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            id: self.id,
+            sequence: self.sequence,
+        }
+    }
+}
+
+impl<S: SealedSignalTrait + 'static> Dirty for WeakSignal<S> {
+    fn count(&self) -> usize {
+        self.upgrade().map_or(0, |signal| signal.count())
+    }
+
+    fn increase(&self) {
+        if let Some(signal) = self.upgrade() {
+            signal.increase();
+        }
+    }
+
+    fn decrease(&self) {
+        if let Some(signal) = self.upgrade() {
+            signal.decrease();
+        }
+    }
+
+    fn reset(&self) {
+        if let Some(signal) = self.upgrade() {
+            signal.reset();
+        }
+    }
+}
+
+impl<S: SealedSignalTrait + 'static> Receptive for WeakSignal<S> {
+    fn react(&self) -> Vec<UpdatePromise> {
+        self.upgrade()
+            .map(|signal| signal.react())
+            .unwrap_or_default()
+    }
+
+    fn enter_mark(&self) -> Option<Vec<Box<dyn Receptive>>> {
+        self.upgrade()?.enter_mark()
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Receptive> {
+        match self.upgrade() {
+            Some(signal) => Box::new(signal),
+            None => Box::new(self.clone()),
+        }
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn sequence(&self) -> usize {
+        self.sequence
+    }
+
+    fn label(&self) -> String {
+        match self.upgrade() {
+            Some(signal) => signal.label(),
+            None => format!("{}@{:#x} (dropped)", any::type_name::<S>(), self.id),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        <S::Rc<State<S>> as Shared>::is_alive(&self.state)
+    }
+
+    fn dependents(&self) -> Vec<Box<dyn Receptive>> {
+        self.upgrade()
+            .map(|signal| signal.dependents())
+            .unwrap_or_default()
+    }
+
+    fn remove_dependent(&self, id: usize) -> bool {
+        self.upgrade()
+            .is_some_and(|signal| signal.remove_dependent(id))
+    }
+
+    fn add_source(&self, source: &mut dyn any::Any) {
+        if let Some(signal) = self.upgrade() {
+            signal.add_source(source);
+        }
+    }
+}
+
+/// Internal trait for signal implementation details.
+///
+/// This trait provides access to the internal fields of a signal. It is only
+/// public so that the associated types can be named through `SignalTrait`.
+#[doc(hidden)]
+pub trait SealedSignalTrait: Receptive {
+    /// The type of the inner value.
+    type Inner;
+
+    /// The type of shared pointer.
+    type Rc<U: ?Sized>: Shared<Inner = U>;
+
+    /// The type that provides interior mutability.
+    type Ptr<U>: InternalMutable<Inner = U>;
+
+    /// The type of function that produces values.
+    ///
+    /// It receives the current value and returns `None` to keep it, in which
+    /// case neither the effect nor the receivers are run.
+    type Processor: Fn(&Self::Inner) -> Option<Self::Inner> + ?Sized + 'static;
+
+    /// The type of function called when a new value is processed.
+    type Effect: Fn(&Self, &Self::Inner) + ?Sized + 'static;
+
+    /// The type of receivers that can depend on this signal.
+    type Receiver: Receptive + ?Sized + 'static;
+
+    /// The type of function run when the signal is dropped.
+    type Cleanup: FnOnce() + ?Sized;
+
+    /// The type of function called with each value stored.
+    ///
+    /// It returns `false` once it is no longer needed, and is then removed.
+    type Hook: Fn(&Self::Inner) -> bool + ?Sized + 'static;
+
+    /// Wrap shared state into a signal handle.
+    fn from_state(state: Self::Rc<State<Self>>) -> Self
+    where
+        Self: Sized;
+
+    /// Access the shared state.
+    fn state(&self) -> &Self::Rc<State<Self>>;
+
+    /// Initialize a new signal with the given components.
+    fn init(
+        inner: Self::Inner,
+        effect: Option<Box<Self::Effect>>,
+        processor: Option<Box<Self::Processor>>,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_state(Self::Rc::<State<Self>>::new(State::new(
+            inner, effect, processor, false,
+        )))
+    }
+
+    /// Access the inner value container, computing a lazy value first.
+    fn inner(&self) -> &Self::Ptr<Self::Inner> {
+        self.evaluate();
+        &self.state().inner
+    }
+
+    /// Access the effect function if present.
+    fn effect(&self) -> Option<&Self::Effect> {
+        self.state().effect.as_deref()
+    }
+
+    /// Access the processor function if present.
+    fn processor(&self) -> Option<&Self::Processor> {
+        self.state().processor.as_deref()
+    }
+
+    /// Access the receivers list.
+    #[allow(clippy::type_complexity)]
+    fn receivers(&self) -> &Self::Ptr<Vec<Box<Self::Receiver>>> {
+        &self.state().receivers
+    }
+
+    /// Access the priorities of the receivers.
+    fn priorities(&self) -> &Self::Ptr<Map<usize, i32>> {
+        &self.state().priorities
+    }
+
+    /// Access the sources list.
+    #[allow(clippy::type_complexity)]
+    fn sources(&self) -> &Self::Ptr<Vec<Box<Self::Receiver>>> {
+        &self.state().sources
+    }
+
+    /// Access the update counter.
+    fn updates(&self) -> &Self::Ptr<usize> {
+        &self.state().updates
+    }
+
+    /// Access the diagnostic name.
+    fn name(&self) -> &Self::Ptr<Option<&'static str>> {
+        &self.state().name
+    }
+
+    /// Access the message of the last caught panic.
+    fn error(&self) -> &Self::Ptr<Option<String>> {
+        &self.state().error
+    }
+
+    /// Access the functions run when the signal is dropped.
+    fn cleanups(&self) -> &Self::Ptr<Vec<Box<Self::Cleanup>>> {
+        &self.state().cleanups
+    }
+
+    /// Access the functions called with each value stored.
+    fn hooks(&self) -> &Self::Ptr<Vec<Box<Self::Hook>>> {
+        &self.state().hooks
+    }
+
+    /// Calls the hooks with a value that was just stored.
+    ///
+    /// Writers call this before releasing the value, so each hook sees every
+    /// value in the order they were stored, even when several threads send to
+    /// a `sync::Signal` at once.
+    fn stored(&self, value: &Self::Inner) {
+        self.hooks().borrow_mut().retain(|hook| hook(value));
+    }
+
+    /// Access the pause count and whether an update was held back.
+    fn paused(&self) -> &Self::Ptr<(usize, bool)> {
+        &self.state().paused
+    }
+
+    /// Access the flag set while a lazy value is yet to be computed.
+    fn lazy(&self) -> &Self::Ptr<bool> {
+        &self.state().lazy
+    }
+
+    /// Computes the value of a lazy signal if that has not happened yet.
+    ///
+    /// The flag stays set while the processor runs, so other readers wait
+    /// for the value, and a panicking processor is retried on the next read.
+    fn evaluate(&self) {
+        if !*self.lazy().borrow() {
+            return;
+        }
+        let mut lazy = self.lazy().borrow_mut();
+        if *lazy {
+            let inner = &self.state().inner;
+            // The reads of the processor belong to this signal, not to a
+            // tracked function that happens to read it first.
+            let value = untrack(|| (self.processor().unwrap())(&inner.borrow()));
+            if let Some(value) = value {
+                *inner.borrow_mut() = value;
+            }
+            *lazy = false;
+        }
+    }
+
+    /// Records that the signal was read or added as a receiver.
+    fn mark_used(&self) {
+        #[cfg(debug_assertions)]
+        self.state().used.store(true, Ordering::Relaxed);
+    }
+
+    /// Records a read of the value, see `Signal::computed`.
+    fn track(&self) {}
+
+    /// Borrows the value mutably for `send` and the other writers.
+    fn inner_mut(&self) -> <Self::Ptr<Self::Inner> as InternalMutable>::RefMut<'_> {
+        self.inner().borrow_mut()
+    }
+}
+
+/// Public interface for reactive signals.
+///
+/// Signals are reactive values that can:
+/// - Hold a value that can be read with `get()` or `borrow()`
+/// - Be updated with new values via `send()`
+/// - Depend on other signals and react to their changes
+/// - Have other signals depend on them through receivers
+///
+/// # Generic code
+///
+/// `reactivity::Signal` and `reactivity::sync::Signal` both implement this
+/// trait, so functions can accept either. Bound the value type with
+/// `SignalTrait<Inner = T>`. Methods registering receivers also need
+/// `S: Clone + Into<Box<S::Receiver>>`, which both signal types satisfy.
+///
+/// ```
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::api::SignalTrait;
+///
+/// fn bump<S: SignalTrait<Inner = i32>>(counter: &S) -> i32 {
+///     counter.update(|count| *count += 1);
+///     counter.with(|count| *count)
+/// }
+///
+/// assert_eq!(bump(&reactivity::Signal::new(1)), 2);
+#[cfg_attr(
+    feature = "std",
+    doc = "assert_eq!(bump(&reactivity::sync::Signal::new(1)), 2);"
+)]
+/// ```
+///
+/// The trait builds on a sealed supertrait holding the implementation. Its
+/// associated types are visible through `SignalTrait`, but only `Inner`, the
+/// type of the value, and `Receiver`, the type of the receivers the signal
+/// accepts, are meant to be named. `Rc`, `Ptr`, `Processor` and `Effect`
+/// describe the internal storage and may change.
+///
+/// Constructors that take closures, such as `driven` and `map`, are inherent
+/// methods of each signal type, as their closures are `Send + Sync` exactly
+/// when the signal is.
+#[allow(private_bounds)]
+pub trait SignalTrait: SealedSignalTrait {
+    fn new(value: Self::Inner) -> Self
+    where
+        Self: Sized,
+        Self::Inner: Sized,
+    {
+        Self::init(value, None, None)
+    }
+
+    /// Creates a signal holding the default value of its type.
+    ///
+    /// Convenient for sources whose real values are sent later.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, Signal};
+    ///
+    /// let names = Signal::<Vec<String>>::new_default();
+    /// assert!(names.get().is_empty());
+    /// ```
+    fn new_default() -> Self
+    where
+        Self: Sized,
+        Self::Inner: Default,
+    {
+        Self::new(Self::Inner::default())
+    }
+
+    /// Creates a signal whose value never changes.
+    ///
+    /// Use it for values that are signals only to fit an API. Sending to a
+    /// constant does nothing, and neither does adding receivers to it, as they
+    /// would never be notified. Reading works as usual.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let limit = Signal::constant(10);
+    /// let clamped = signal!([limit] limit.min(3));
+    /// assert_eq!(limit.receiver_count(), 0);
+    ///
+    /// limit.send(1);
+    /// assert_eq!(limit.get(), 10);
+    /// assert_eq!(clamped.get(), 3);
+    /// ```
+    fn constant(value: Self::Inner) -> Self
+    where
+        Self: Sized,
+        Self::Inner: Sized,
+    {
+        Self::from_state(Self::Rc::<State<Self>>::new(State::new(
+            value, None, None, true,
+        )))
+    }
+
+    /// Returns whether this signal was created with `constant`.
+    fn is_constant(&self) -> bool {
+        self.state().constant
+    }
+    /// Returns a clone of the current value.
+    ///
+    /// Only this convenience needs `Clone`. Use `with` or `borrow` to read
+    /// values that cannot be cloned.
+    fn get(&self) -> Self::Inner
+    where
+        Self::Inner: Clone,
+    {
+        self.mark_used();
+        self.track();
+        self.inner().borrow().clone()
+    }
+    /// Returns a copy of the current value.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let a = signal!(2);
+    /// let b = signal!(3);
+    /// let sum = &a + &b;
+    ///
+    /// a.send(4);
+    /// assert_eq!(sum.value(), 7);
+    /// ```
+    fn value(&self) -> Self::Inner
+    where
+        Self::Inner: Copy,
+    {
+        self.mark_used();
+        self.track();
+        *self.inner().borrow()
+    }
+    /// Returns a clone of the current value without establishing a dependency.
+    ///
+    /// Use this inside a processor to read a signal that is not one of its
+    /// dependencies. Peeked reads never register a receiver, so changes to the
+    /// peeked signal will not cause the reading signal to recompute. For
+    /// `sync::Signal` only a read lock is taken.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(1);
+    /// let factor = signal!(10);
+    /// let factor_ = factor.clone();
+    /// let scaled = signal!([count] count * factor_.peek());
+    ///
+    /// // Changing the peeked signal does not recompute `scaled`
+    /// factor.send(100);
+    /// assert_eq!(scaled.get(), 10);
+    ///
+    /// count.send(2);
+    /// assert_eq!(scaled.get(), 200);
+    /// ```
+    fn peek(&self) -> Self::Inner
+    where
+        Self::Inner: Clone,
+    {
+        self.mark_used();
+        self.inner().borrow().clone()
+    }
+    /// Runs `f` with a reference to the current value and returns its result.
+    ///
+    /// Unlike `get`, the value is never cloned, which makes reading a part of a
+    /// large value cheap.
+    ///
+    /// For `sync::Signal`, `f` runs while the read lock is held, so it must not
+    /// send to the same signal or it will deadlock.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let names = signal!(vec!["apples".to_string(), "pears".to_string()]);
+    /// assert_eq!(names.with(|names| names.len()), 2);
+    /// ```
+    fn with<R>(&self, f: impl FnOnce(&Self::Inner) -> R) -> R {
+        self.mark_used();
+        self.track();
+        f(&self.inner().borrow())
+    }
+    /// Returns a clone of the current value, or an error if it is mutably
+    /// borrowed.
+    ///
+    /// For `sync::Signal` this does not block on a writer.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(1);
+    /// let guard = count.borrow_mut();
+    /// assert!(count.try_get().is_err());
+    ///
+    /// drop(guard);
+    /// assert_eq!(count.try_get(), Ok(1));
+    /// ```
+    fn try_get(&self) -> Result<Self::Inner, BorrowError>
+    where
+        Self::Inner: Clone,
+    {
+        self.try_with(Clone::clone)
+    }
+    /// Runs `f` with a reference to the current value like `with`, or returns
+    /// an error if the value is mutably borrowed.
+    fn try_with<R>(&self, f: impl FnOnce(&Self::Inner) -> R) -> Result<R, BorrowError> {
+        self.try_borrow().map(|value| f(&value))
+    }
+    /// Borrows the current value, or returns an error if it is mutably borrowed.
+    fn try_borrow(
+        &self,
+    ) -> Result<<Self::Ptr<Self::Inner> as InternalMutable>::Ref<'_>, BorrowError> {
+        self.mark_used();
+        self.track();
+        self.inner().try_borrow().ok_or(BorrowError)
+    }
+    /// Returns the number of dependency updates this signal is still waiting for.
+    ///
+    /// This is zero whenever no propagation through the signal is in progress.
+    /// Updates propagate on the thread that sent them, so for `sync::Signal`
+    /// only those in progress on the calling thread are counted.
+    fn pending_updates(&self) -> usize {
+        pending(self.id())
+    }
+    /// Clears the pending updates of this signal and of the receivers waiting
+    /// on it, without recomputing them.
+    ///
+    /// A signal left waiting for an update that will never arrive ignores
+    /// every later one too. With `std`, the signals of an update are reset
+    /// when an effect panics. This is the escape hatch for the cases that are
+    /// not covered, such as `no_std` targets. Values are left as they are, so
+    /// receivers catch up on the next update.
+    fn force_settle(&self)
+    where
+        Self: Sized,
+    {
+        abandon(self);
+    }
+    /// Returns how many times the signal has taken a new value.
+    ///
+    /// Counts every notification of a sent value, and every recomputation
+    /// whose value was kept. Recomputations rejected by `driven_eq` and the
+    /// like are not counted.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let x = signal!(1);
+    /// let doubled = signal!([x] x * 2);
+    /// let tripled = signal!([x] x * 3);
+    /// let sum = signal!([doubled, tripled] doubled + tripled);
+    ///
+    /// x.send(2);
+    /// x.send(3);
+    /// assert_eq!(x.update_count(), 2);
+    /// assert_eq!(sum.update_count(), 2);
+    /// ```
+    fn update_count(&self) -> usize {
+        *self.updates().borrow()
+    }
+    /// Returns whether the signal is waiting for dependency updates.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Batch, Signal};
+    ///
+    /// let count = signal!(0);
+    /// let doubled = signal!([count] count * 2);
+    ///
+    /// let batch = Batch::new();
+    /// count.send(1);
+    /// assert!(doubled.is_dirty());
+    ///
+    /// drop(batch);
+    /// assert!(!doubled.is_dirty());
+    /// ```
+    fn is_dirty(&self) -> bool {
+        self.pending_updates() > 0
+    }
+    fn borrow(&self) -> <Self::Ptr<Self::Inner> as InternalMutable>::Ref<'_> {
+        self.mark_used();
+        self.track();
+        self.inner().borrow()
+    }
+    fn borrow_mut(&self) -> <Self::Ptr<Self::Inner> as InternalMutable>::RefMut<'_> {
+        self.mark_used();
+        self.inner_mut()
+    }
+    /// Names the signal for diagnostics such as `debug_graph` and cycle errors.
+    fn named(self, name: &'static str) -> Self
+    where
+        Self: Sized,
+    {
+        *self.name().borrow_mut() = Some(name);
+        self
+    }
+
+    /// Renders this signal and every signal depending on it as a Graphviz DOT
+    /// graph, with an edge from each signal to each of its receivers.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(1).named("count");
+    /// let doubled = signal!([count] count * 2).named("doubled");
+    ///
+    /// let dot = count.debug_graph();
+    /// assert!(dot.contains("[label=\"doubled\"]"));
+    /// println!("{dot}");
+    /// ```
+    fn debug_graph(&self) -> String {
+        let mut dot = String::from("digraph signals {\n");
+        let mut visited = Set::new();
+        let mut pending = vec![self.clone_boxed()];
+        while let Some(node) = pending.pop() {
+            if !visited.insert(node.id()) {
+                continue;
+            }
+            let _ = writeln!(dot, "    n{:x} [label={:?}];", node.id(), node.label());
+            for dependent in node.dependents() {
+                let _ = writeln!(dot, "    n{:x} -> n{:x};", node.id(), dependent.id());
+                pending.push(dependent);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Registers a dependent signal that will react when this signal changes.
+    ///
+    /// This method accepts anything that can be converted into `Box<Self::Receiver>`,
+    /// so you can pass signal instances directly without manually boxing them.
+    /// Only a weak handle is kept, and the receiver removes itself once its
+    /// last handle is dropped.
+    ///
+    /// This can be called while an update propagates, for example from an
+    /// effect. Each update works from a snapshot of the receivers taken when
+    /// it was sent, so the new receiver does not react to that update and
+    /// joins from the next one.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(0);
+    /// let source = count.clone();
+    /// let doubled = Signal::driven(move || source.get() * 2, |_, _| {});
+    ///
+    /// // Add doubled as a receiver of count
+    /// // doubled will update automatically when count changes
+    /// count.add_receiver(doubled.clone());
+    /// assert_eq!(count.receiver_count(), 1);
+    ///
+    /// drop(doubled);
+    /// assert_eq!(count.receiver_count(), 0);
+    /// ```
+    fn add_receiver(&self, receiver: impl Into<Box<Self::Receiver>>)
+    where
+        Self: Clone + Into<Box<Self::Receiver>>,
+    {
+        self.add_receiver_with_priority(receiver, 0);
+    }
+
+    /// Registers a dependent signal that reacts before the receivers of a
+    /// lower priority.
+    ///
+    /// Receivers are notified from the highest priority down, and in the
+    /// order they were added within a priority. `add_receiver` uses priority
+    /// zero. Use this when one effect sets up state that another reads, for
+    /// example to invalidate a cache before a repaint. The priority only
+    /// orders the receivers of this signal, and the whole graph still
+    /// settles before `send` returns.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use std::{cell::RefCell, rc::Rc};
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let log = Rc::new(RefCell::new(Vec::new()));
+    /// let count = signal!(0);
+    /// let watcher = |name: &'static str| {
+    ///     let log = log.clone();
+    ///     Signal::driven(|| (), move |_, _| log.borrow_mut().push(name))
+    /// };
+    /// let repaint = watcher("repaint");
+    /// let invalidate = watcher("invalidate");
+    /// count.add_receiver(repaint.clone());
+    /// count.add_receiver_with_priority(invalidate.clone(), 10);
+    ///
+    /// count.send(1);
+    /// assert_eq!(*log.borrow(), ["invalidate", "repaint"]);
+    /// ```
+    fn add_receiver_with_priority(&self, receiver: impl Into<Box<Self::Receiver>>, priority: i32)
+    where
+        Self: Clone + Into<Box<Self::Receiver>>,
+    {
+        if self.is_constant() {
+            return;
+        }
+        self.evaluate();
+        let receiver = receiver.into();
+        let source: Box<Self::Receiver> = self.clone().into();
+        receiver.add_source(&mut Some(source));
+        insert_receiver(self, receiver, priority);
+    }
+
+    /// Unregisters a receiver previously added with `add_receiver`.
+    ///
+    /// Receivers are matched by identity, so any clone of the receiver can be
+    /// passed. If the same receiver was added several times, only one instance
+    /// is removed. Returns whether a receiver was removed.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(0);
+    /// let doubled = signal!([count] count * 2);
+    ///
+    /// assert!(count.remove_receiver(&doubled));
+    /// count.send(5);
+    /// assert_eq!(doubled.get(), 0);
+    /// ```
+    fn remove_receiver(&self, receiver: &impl Receptive) -> bool {
+        let removed = remove_by_id(&mut self.receivers().borrow_mut(), receiver.id());
+        removed.is_some()
+    }
+
+    /// Returns the number of receivers that still exist.
+    ///
+    /// Dropped receivers are removed from the list the next time this signal
+    /// propagates an update.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(0);
+    /// let doubled = signal!([count] count * 2);
+    /// assert_eq!(count.receiver_count(), 1);
+    ///
+    /// drop(doubled);
+    /// assert_eq!(count.receiver_count(), 0);
+    /// ```
+    fn receiver_count(&self) -> usize {
+        self.receivers()
+            .borrow()
+            .iter()
+            .filter(|receiver| receiver.is_alive())
+            .count()
+    }
+
+    /// Returns the number of handles keeping this signal alive.
+    ///
+    /// Clones count, and so do the processors of derived signals that hold a
+    /// clone of a dependency. Once every derived signal is dropped the count
+    /// drops back, so a count that stays up points at a cycle.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(0);
+    /// let doubled = signal!([count] count * 2);
+    /// assert_eq!(count.strong_count(), 2);
+    ///
+    /// drop(doubled);
+    /// assert_eq!(count.strong_count(), 1);
+    /// ```
+    fn strong_count(&self) -> usize {
+        <Self::Rc<State<Self>> as Shared>::strong_count(self.state())
+    }
+
+    /// Returns the number of weak handles to this signal.
+    ///
+    /// These include the entries for this signal in the receiver lists of its
+    /// sources and every `WeakSignal` created with `downgrade`.
+    fn weak_count(&self) -> usize {
+        <Self::Rc<State<Self>> as Shared>::weak_count(self.state())
+    }
+
+    /// Returns the ids of the receivers that still exist, in the order they
+    /// were added.
+    ///
+    /// Ids are the ones returned by `Receptive::id`, which all clones of a
+    /// signal share, so tests can check how a graph is wired. Only available
+    /// with the `testing` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use reactivity::{api::{Receptive, SignalTrait}, signal, Signal};
+    ///
+    /// let count = signal!(0);
+    /// let doubled = signal!([count] count * 2);
+    /// assert_eq!(count.receiver_ids(), vec![doubled.id()]);
+    /// ```
+    #[cfg(feature = "testing")]
+    fn receiver_ids(&self) -> Vec<usize> {
+        self.dependents()
+            .iter()
+            .map(|receiver| receiver.id())
+            .collect()
+    }
+
+    /// Returns the labels of the receivers that still exist, see
+    /// `receiver_ids` and `named`. Only available with the `testing` feature.
+    #[cfg(feature = "testing")]
+    fn receiver_labels(&self) -> Vec<String> {
+        self.dependents()
+            .iter()
+            .map(|receiver| receiver.label())
+            .collect()
+    }
+
+    /// Registers a dependent signal and returns a handle that unregisters it
+    /// when dropped.
+    ///
+    /// The handle only holds a weak reference to this signal, so it does not
+    /// keep the signal alive. Call `Subscription::forget` to keep the
+    /// receiver registered for good, as `add_receiver` does.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(0);
+    /// let source = count.clone();
+    /// let doubled = Signal::driven(move || source.get() * 2, |_, _| {});
+    ///
+    /// let subscription = count.subscribe(doubled.clone());
+    /// count.send(1);
+    /// assert_eq!(doubled.get(), 2);
+    ///
+    /// drop(subscription);
+    /// count.send(2);
+    /// assert_eq!(doubled.get(), 2);
+    /// ```
+    fn subscribe(&self, receiver: impl Into<Box<Self::Receiver>>) -> Subscription<Self::Receiver>
+    where
+        Self: Clone + Into<Box<Self::Receiver>>,
+    {
+        let receiver = receiver.into();
+        let id = receiver.id();
+        if self.is_constant() {
+            return Subscription { source: None, id };
+        }
+        self.evaluate();
+        let source: Box<Self::Receiver> = self.clone().into();
+        receiver.add_source(&mut Some(source));
+        insert_receiver(self, receiver, 0);
+        Subscription {
+            source: Some(self.clone().into()),
+            id,
+        }
+    }
+
+    /// Creates a weak handle to this signal, see `WeakSignal`.
+    fn downgrade(&self) -> WeakSignal<Self>
+    where
+        Self: Sized,
+    {
+        WeakSignal {
+            state: <Self::Rc<State<Self>> as Shared>::downgrade(self.state()),
+            id: self.id(),
+            sequence: self.state().sequence,
+        }
+    }
+
+    /// Creates a signal and returns it split into a read half and a write half.
+    ///
+    /// Pass each half to the code that needs that capability. The halves
+    /// share the signal, which lives for as long as either of them does.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let (count, set_count) = Signal::split(1);
+    /// let doubled = signal!([count] count * 2);
+    ///
+    /// set_count.send(4);
+    /// drop(count);
+    /// assert_eq!(doubled.get(), 8);
+    /// ```
+    fn split(value: Self::Inner) -> (ReadSignal<Self>, WriteSignal<Self>)
+    where
+        Self: Clone,
+        Self::Inner: Sized,
+    {
+        let signal = Self::new(value);
+        (signal.read_only(), WriteSignal { signal })
+    }
+
+    /// Creates a read-only view of this signal, see `ReadSignal`.
+    fn read_only(&self) -> ReadSignal<Self>
+    where
+        Self: Clone,
+    {
+        ReadSignal {
+            signal: self.clone(),
+        }
+    }
+
+    /// Stores a new value and notifies receivers.
+    ///
+    /// The value is only borrowed mutably while it is replaced. For
+    /// `sync::Signal` the write lock is released before the old value is
+    /// dropped and before any receiver reacts, so effects may read or send to
+    /// other signals without holding this signal's lock.
+    ///
+    /// Returns the promises of the receivers, which resolve when dropped.
+    /// Sent from an effect, or otherwise while another update is settling,
+    /// the value is stored right away but only propagates once that update
+    /// is done, so an effect can send to its own source without losing
+    /// either update.
+    ///
+    /// # Panics
+    ///
+    /// For `Signal`, panics if the value is borrowed, such as by `with` or a
+    /// guard from `borrow` that is still alive. Use `Signal::try_send` to get
+    /// the value back instead. A `sync::Signal` waits for the borrow to end,
+    /// so sending while the same thread holds one never returns.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(1);
+    /// let doubled = signal!([count] count * 2);
+    ///
+    /// count.send(2);
+    /// assert_eq!(doubled.get(), 4);
+    /// ```
+    fn send(&self, value: Self::Inner) -> Vec<UpdatePromise>
+    where
+        Self: Sized,
+    {
+        if self.is_constant() {
+            return Vec::new();
+        }
+        let mut inner = self.inner_mut();
+        let old = mem::replace(&mut *inner, value);
+        self.stored(&inner);
+        drop(inner);
+        drop(old);
+        propagate(self)
+    }
+
+    /// Computes the new value from the current one and sends it.
+    ///
+    /// The value stays mutably borrowed while `f` runs, so for `sync::Signal`
+    /// no other thread can update it in between, unlike with
+    /// `signal.send(signal.get() + 1)`. `f` must not access this signal.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(1);
+    /// count.send_with(|count| count + 1);
+    /// assert_eq!(count.get(), 2);
+    /// ```
+    fn send_with(&self, f: impl FnOnce(&Self::Inner) -> Self::Inner) -> Vec<UpdatePromise>
+    where
+        Self: Sized,
+    {
+        if self.is_constant() {
+            return Vec::new();
+        }
+        let mut inner = self.inner_mut();
+        let value = f(&inner);
+        let old = mem::replace(&mut *inner, value);
+        self.stored(&inner);
+        drop(inner);
+        drop(old);
+        propagate(self)
+    }
+
+    /// Stores a new value and returns the previous one, like `Cell::replace`.
+    ///
+    /// The swap happens under a single mutable borrow, and receivers are then
+    /// notified as by `send`, seeing the new value. The update has settled by
+    /// the time this returns. A constant keeps its value, and `value` is
+    /// handed back.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let buffer = signal!(vec![1, 2, 3]);
+    /// let len = signal!([buffer] buffer.len());
+    ///
+    /// let mut old = buffer.replace(vec![4]);
+    /// assert_eq!(old, vec![1, 2, 3]);
+    /// assert_eq!(len.get(), 1);
+    ///
+    /// // Reuse the old allocation
+    /// old.clear();
+    /// ```
+    fn replace(&self, value: Self::Inner) -> Self::Inner
+    where
+        Self: Sized,
+    {
+        if self.is_constant() {
+            return value;
+        }
+        let mut inner = self.inner_mut();
+        let old = mem::replace(&mut *inner, value);
+        self.stored(&inner);
+        drop(inner);
+        drop(propagate(self));
+        old
+    }
+
+    /// Resets the value to its default and returns the previous one, like
+    /// `Cell::take`.
+    ///
+    /// This is `replace` with `Default::default()`, so receivers are notified
+    /// the same way.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let event = signal!(Some("clicked"));
+    /// assert_eq!(event.take(), Some("clicked"));
+    /// assert_eq!(event.get(), None);
+    /// ```
+    fn take(&self) -> Self::Inner
+    where
+        Self: Sized,
+        Self::Inner: Default,
+    {
+        self.replace(Default::default())
+    }
+
+    /// Sends a value only if it differs from the current one.
+    ///
+    /// The comparison and the store happen under a single write borrow, so for
+    /// `sync::Signal` no other thread can change the value in between. Returns
+    /// whether the value changed and receivers were notified.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(1);
+    /// assert!(!count.set_if_changed(1));
+    /// assert!(count.set_if_changed(2));
+    /// assert_eq!(count.get(), 2);
+    /// ```
+    fn set_if_changed(&self, value: Self::Inner) -> bool
+    where
+        Self: Sized,
+        Self::Inner: PartialEq,
+    {
+        if self.is_constant() {
+            return false;
+        }
+        let mut inner = self.inner_mut();
+        if *inner == value {
+            return false;
+        }
+        *inner = value;
+        self.stored(&inner);
+        drop(inner);
+        drop(propagate(self));
+        true
+    }
+
+    /// Sends a value after checking that the receivers contain no cycle.
+    ///
+    /// `send` panics when an update runs into a cycle of signals depending on
+    /// each other. This variant searches the graph first and returns the cycle
+    /// as an error instead, leaving the value unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, Signal};
+    ///
+    /// let a = Signal::new(1);
+    /// let a_ = a.clone();
+    /// let b = Signal::driven(move || a_.get() + 1, |_, _| {});
+    /// let b_ = b.clone();
+    /// let c = Signal::driven(move || b_.get() + 1, |_, _| {});
+    /// b.add_receiver(c.clone());
+    /// c.add_receiver(b.clone());
+    ///
+    /// a.add_receiver(b);
+    /// assert!(a.send_checked(2).is_err());
+    /// assert_eq!(a.get(), 1);
+    /// ```
+    fn send_checked(&self, value: Self::Inner) -> Result<(), CycleError>
+    where
+        Self: Sized,
+    {
+        if let Some(cycle) = CycleError::find(self.clone_boxed()) {
+            return Err(cycle);
+        }
+        drop(self.send(value));
+        Ok(())
+    }
+
+    /// Mutates the value in place and notifies receivers like `send`.
+    ///
+    /// The write borrow is released before any receiver reacts, so effects may
+    /// read this signal again.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let items = signal!(vec![1, 2]);
+    /// let len = signal!([items] items.len());
+    ///
+    /// items.update(|items| items.push(3));
+    /// assert_eq!(len.get(), 3);
+    /// ```
+    fn update(&self, f: impl FnOnce(&mut Self::Inner)) -> Vec<UpdatePromise>
+    where
+        Self: Sized,
+    {
+        if self.is_constant() {
+            return Vec::new();
+        }
+        let mut inner = self.inner_mut();
+        f(&mut inner);
+        self.stored(&inner);
+        drop(inner);
+        propagate(self)
+    }
+
+    /// Holds back the updates of this signal until `resume` is called.
+    ///
+    /// A paused signal still takes the values sent to it, and a derived one
+    /// still recomputes, but its receivers are not notified. Pauses nest: each
+    /// `pause` needs its own `resume`, and the last `resume` notifies the
+    /// receivers once if the signal was updated in the meantime. Unlike
+    /// `batch`, which holds back every update on the thread, this only affects
+    /// what comes after one signal.
+    ///
+    /// # Example
+    /// ```
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{api::SignalTrait, signal, Signal};
+    ///
+    /// let count = signal!(0);
+    /// let doubled = signal!([count] count * 2);
+    ///
+    /// count.pause();
+    /// count.send(1);
+    /// count.send(2);
+    /// assert_eq!(doubled.get(), 0);
+    ///
+    /// count.resume();
+    /// assert_eq!(doubled.get(), 4);
+    /// ```
+    fn pause(&self) {
+        self.paused().borrow_mut().0 += 1;
+    }
+
+    /// Ends a `pause`, notifying the receivers if it was the last one and the
+    /// signal was updated while paused.
+    ///
+    /// Returns the promises of the held back update, like `send`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the signal is not paused.
+    fn resume(&self) -> Vec<UpdatePromise>
+    where
+        Self: Sized,
+    {
+        let mut paused = self.paused().borrow_mut();
+        assert!(
+            paused.0 > 0,
+            "`resume` called on a signal that is not paused"
+        );
+        paused.0 -= 1;
+        let held_back = paused.0 == 0 && mem::take(&mut paused.1);
+        drop(paused);
+        if held_back {
+            propagate(self)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Returns whether the signal is paused, see `pause`.
+    fn is_paused(&self) -> bool {
+        self.paused().borrow().0 > 0
+    }
+
+    /// Notifies receivers that the value has changed.
+    ///
+    /// This is the propagation step shared by `send` and `update`. Call it
+    /// yourself after mutating the value through `borrow_mut`, which also
+    /// hands the value to listeners such as `Signal::on_value`. Inside a
+    /// `Batch` the promises are withheld and an empty list is returned. With
+    /// `Order::BreadthFirst` the update settles before this returns, and the
+    /// list is empty as well.
+    fn notify(&self) -> Vec<UpdatePromise>
+    where
+        Self: Sized,
+    {
+        if self.is_constant() {
+            return Vec::new();
+        }
+        if !self.hooks().borrow().is_empty() {
+            self.stored(&self.inner().borrow());
+        }
+        propagate(self)
+    }
+}
+
+/// Notifies the receivers of a signal whose new value was already stored,
+/// see `SignalTrait::notify`.
+pub(crate) fn propagate<S: SignalTrait>(signal: &S) -> Vec<UpdatePromise> {
+    if signal.is_constant() {
+        return Vec::new();
+    }
+    let mut paused = signal.paused().borrow_mut();
+    if paused.0 > 0 {
+        paused.1 = true;
+        return Vec::new();
+    }
+    drop(paused);
+    // The borrow is released before the promises resolve, so effects are
+    // free to add or remove receivers of this signal.
+    *signal.updates().borrow_mut() += 1;
+    prune(signal);
+    let receivers = signal.dependents();
+    trace!(signal = %signal.label(), receivers = receivers.len(), "update sent");
+    let marking = Marking::enter(signal.clone_boxed());
+    let promises = receivers
+        .iter()
+        .map(|receiver| {
+            mark_stale(receiver.id());
+            let promise = receiver.promise();
+            trace!(
+                receiver = %receiver.label(),
+                dirty = receiver.count(),
+                "receiver notified"
+            );
+            promise
+        })
+        .collect::<Vec<_>>();
+    drop(marking);
+    if Batch::is_active() {
+        Batch::defer(promises);
+        return Vec::new();
+    }
+    if propagation_order() == Order::BreadthFirst {
+        settle(promises);
+        return Vec::new();
+    }
+    promises
+}
+
+impl<T: SealedSignalTrait> SignalTrait for T {}
+
+impl<T: SignalTrait + 'static> From<T> for Box<dyn Receptive> {
+    fn from(signal: T) -> Self {
+        Box::new(signal.downgrade())
+    }
+}
+
+impl<T: SignalTrait + 'static> From<T> for Box<dyn Receptive + Send + Sync>
+where
+    WeakSignal<T>: Send + Sync,
+{
     fn from(signal: T) -> Self {
-        Box::new(signal)
+        Box::new(signal.downgrade())
     }
 }
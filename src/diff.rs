@@ -0,0 +1,173 @@
+use crate::api::SignalTrait;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+use core::cell::RefCell;
+
+/// A change between two versions of a list, see `Signal::diff_map`.
+///
+/// `Removed` indices and `Moved::from` refer to the old list, `Added` indices
+/// and `Moved::to` to the new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Diff<T> {
+    /// An item with a new key.
+    Added { index: usize, value: T },
+    /// An item whose key is gone.
+    Removed { index: usize, value: T },
+    /// An item that changed places relative to the items around it.
+    Moved { from: usize, to: usize, value: T },
+}
+
+/// Computes the changes from `old` to `new`, matching items by `key`.
+///
+/// Removals come first in the order of the old list, followed by additions
+/// and moves in the order of the new list. Of the items present in both, the
+/// largest set that kept its relative order stays put and the others are
+/// reported as moved, so appending or removing one item never moves the
+/// rest. Keys are expected to be unique within a list.
+///
+/// # Example
+/// ```
+/// use reactivity::diff::{diff, Diff};
+///
+/// let changes = diff(&["a", "b", "c"], &["c", "a", "d"], |item| *item);
+/// assert_eq!(
+///     changes,
+///     vec![
+///         Diff::Removed { index: 1, value: "b" },
+///         Diff::Moved { from: 2, to: 0, value: "c" },
+///         Diff::Added { index: 2, value: "d" },
+///     ]
+/// );
+/// ```
+pub fn diff<T: Clone, K: Ord>(old: &[T], new: &[T], key: impl Fn(&T) -> K) -> Vec<Diff<T>> {
+    let old_indices: BTreeMap<K, usize> = old
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (key(item), i))
+        .collect();
+    let new_keys: Vec<K> = new.iter().map(&key).collect();
+    let kept: BTreeSet<&K> = new_keys.iter().collect();
+
+    let mut changes: Vec<_> = old
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| !kept.contains(&key(item)))
+        .map(|(index, item)| Diff::Removed {
+            index,
+            value: item.clone(),
+        })
+        .collect();
+
+    let from: Vec<Option<usize>> = new_keys
+        .iter()
+        .map(|key| old_indices.get(key).copied())
+        .collect();
+    let common: Vec<usize> = from.iter().flatten().copied().collect();
+    let mut staying = longest_increasing(&common).into_iter();
+    for (to, (item, from)) in new.iter().zip(from).enumerate() {
+        let value = item.clone();
+        match from {
+            None => changes.push(Diff::Added { index: to, value }),
+            Some(from) => {
+                if !staying.next().unwrap_or(false) {
+                    changes.push(Diff::Moved { from, to, value });
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// Marks the members of a longest strictly increasing subsequence.
+fn longest_increasing(values: &[usize]) -> Vec<bool> {
+    // Index of the smallest last value of an increasing run of each length,
+    // and the element before each one in its run
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+    for (i, &value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&tail| values[tail] < value);
+        previous[i] = length.checked_sub(1).map(|before| tails[before]);
+        if length == tails.len() {
+            tails.push(i);
+        } else {
+            tails[length] = i;
+        }
+    }
+    let mut members = vec![false; values.len()];
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        members[i] = true;
+        next = previous[i];
+    }
+    members
+}
+
+impl<T: Clone + 'static> crate::Signal<Vec<T>> {
+    /// Creates a signal holding the changes of each update of this list.
+    ///
+    /// Items are matched by `key`, see `diff`. The new signal starts with an
+    /// `Added` change for each current item, so a view can be built from it,
+    /// and then holds the changes of the latest update. Updates that change
+    /// nothing are not passed on.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{diff::Diff, Signal};
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let todos = Signal::new(vec![(1, "write"), (2, "test")]);
+    /// let changes = todos.diff_map(|(id, _)| *id);
+    ///
+    /// todos.update(|todos| todos.push((3, "ship")));
+    /// assert_eq!(changes.get(), vec![Diff::Added { index: 2, value: (3, "ship") }]);
+    /// ```
+    pub fn diff_map<K: Ord>(&self, key: impl Fn(&T) -> K + 'static) -> crate::Signal<Vec<Diff<T>>> {
+        let source = self.clone();
+        let last = RefCell::new(self.get());
+        let initial = diff(&[], &last.borrow(), &key);
+        let signal = crate::Signal::derived(
+            initial,
+            move |_| {
+                let new = source.get();
+                let changes = diff(&last.borrow(), &new, &key);
+                *last.borrow_mut() = new;
+                (!changes.is_empty()).then_some(changes)
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Clone + Send + Sync + 'static> crate::sync::Signal<Vec<T>> {
+    /// Creates a signal holding the changes of each update of this list, see
+    /// `reactivity::Signal::diff_map`.
+    pub fn diff_map<K: Ord>(
+        &self,
+        key: impl Fn(&T) -> K + Send + Sync + 'static,
+    ) -> crate::sync::Signal<Vec<Diff<T>>> {
+        let source = self.clone();
+        let last = crate::lock::Mutex::new(self.get());
+        let initial = diff(&[], &last.lock(), &key);
+        let signal = crate::sync::Signal::derived(
+            initial,
+            move |_| {
+                let new = source.get();
+                let mut last = last.lock();
+                let changes = diff(&last, &new, &key);
+                *last = new;
+                (!changes.is_empty()).then_some(changes)
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+}
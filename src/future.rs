@@ -0,0 +1,312 @@
+use crate::{
+    api::{SignalTrait, WeakSignal},
+    lock::Mutex,
+    sync::Signal,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{Context, Poll, Waker},
+};
+
+/// The state of a value produced by a future.
+///
+/// Held by signals created with `Signal::from_future`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Async<T> {
+    /// The future has not completed yet.
+    Pending,
+    /// The future completed with this value.
+    Ready(T),
+}
+
+impl<T> Async<T> {
+    /// Returns whether the future has not completed yet.
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Async::Pending)
+    }
+
+    /// Returns whether the future has completed.
+    pub fn is_ready(&self) -> bool {
+        matches!(self, Async::Ready(_))
+    }
+
+    /// Returns the value if the future has completed.
+    pub fn ready(&self) -> Option<&T> {
+        match self {
+            Async::Pending => None,
+            Async::Ready(value) => Some(value),
+        }
+    }
+}
+
+/// A boxed task handed to the spawn function of `Signal::from_future`.
+pub type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+impl<T: Send + Sync + 'static> Signal<Async<T>> {
+    /// Creates a signal that is pending until `future` completes.
+    ///
+    /// The future is wrapped into a task and handed to `spawn`, which should
+    /// run it on the executor of your choice. When it completes, its output is
+    /// sent to the signal as `Async::Ready`, so receivers react to the data
+    /// arriving. The task only holds a weak handle: if the signal has been
+    /// dropped by then, the output is discarded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::{future::Future, pin::pin, task::{Context, Poll, Waker}};
+    /// use reactivity::future::Async;
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let mut task = None;
+    /// let data = Signal::from_future(async { 42 }, |spawned| task = Some(spawned));
+    /// assert_eq!(data.get(), Async::Pending);
+    ///
+    /// // Stands in for an executor
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// let _ = task.unwrap().as_mut().poll(&mut cx);
+    /// assert_eq!(data.get(), Async::Ready(42));
+    /// ```
+    pub fn from_future<F>(future: F, spawn: impl FnOnce(Task)) -> Self
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let signal = Signal::new(Async::Pending);
+        let weak = signal.downgrade();
+        spawn(Box::pin(async move {
+            let value = future.await;
+            if let Some(signal) = weak.upgrade() {
+                drop(signal.send(Async::Ready(value)));
+            }
+        }));
+        signal
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Signal<T> {
+    /// Creates a signal holding the output of the async function `f` for the
+    /// latest value of this signal.
+    ///
+    /// `f` is called with the current value right away and again each time
+    /// this signal updates. Each future it returns is wrapped into a task and
+    /// handed to `spawn`, like in `Signal::from_future`. The new signal is
+    /// pending until the first task completes, and then keeps the last output
+    /// until the task started for a newer value completes.
+    ///
+    /// A new value supersedes the task still running for an older one: that
+    /// task is woken, drops its future without polling it again and finishes,
+    /// and an output it had already produced is discarded. Outputs of older
+    /// values therefore never overwrite the output of a newer one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::{future::Future, sync::{Arc, Mutex}, task::{Context, Waker}};
+    /// use reactivity::future::Async;
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let tasks = Arc::new(Mutex::new(Vec::new()));
+    /// let tasks_ = tasks.clone();
+    /// let id = Signal::new(1);
+    /// let user = id.map_async(
+    ///     |id| async move { format!("user {id}") },
+    ///     move |task| tasks_.lock().unwrap().push(task),
+    /// );
+    /// assert_eq!(user.get(), Async::Pending);
+    ///
+    /// id.send(2);
+    /// // Stands in for an executor
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// for mut task in tasks.lock().unwrap().drain(..) {
+    ///     let _ = task.as_mut().poll(&mut cx);
+    /// }
+    /// assert_eq!(user.get(), Async::Ready("user 2".to_string()));
+    /// ```
+    pub fn map_async<U, Fut>(
+        &self,
+        f: impl Fn(T) -> Fut + Send + Sync + 'static,
+        spawn: impl Fn(Task) + Send + Sync + 'static,
+    ) -> Signal<Async<U>>
+    where
+        U: Send + Sync + 'static,
+        Fut: Future<Output = U> + Send + 'static,
+    {
+        let latest = Arc::new(Latest::default());
+        // Tasks send their output to the signal that starts them, which only
+        // exists once the processor has been moved into it.
+        let this = Arc::new(OnceLock::<WeakSignal<Signal<Async<U>>>>::new());
+        let start = {
+            let this = this.clone();
+            Arc::new(move |value: T| {
+                let generation = latest.supersede();
+                let computation = Cancellable {
+                    future: Box::pin(f(value)),
+                    generation,
+                    latest: latest.clone(),
+                };
+                let (this, latest) = (this.clone(), latest.clone());
+                spawn(Box::pin(async move {
+                    let Some(output) = computation.await else {
+                        return;
+                    };
+                    // Checking and sending in one step keeps a task that was
+                    // superseded in between from sending after the newer one.
+                    let _sending = latest.sending.lock();
+                    if latest.flight.lock().generation != generation {
+                        return;
+                    }
+                    if let Some(signal) = this.get().and_then(WeakSignal::upgrade) {
+                        drop(signal.send(Async::Ready(output)));
+                    }
+                }));
+            })
+        };
+        let (source, start_) = (self.clone(), start.clone());
+        let signal = Signal::derived(
+            Async::Pending,
+            move |_| {
+                start_(source.get());
+                None
+            },
+            |_, _| {},
+        );
+        let _ = this.set(signal.downgrade());
+        self.add_receiver(signal.clone());
+        start(self.get());
+        signal
+    }
+}
+
+/// The computations started by `Signal::map_async`.
+#[derive(Default)]
+struct Latest {
+    flight: Mutex<Flight>,
+    /// Held by a task while it checks that it is current and sends its output
+    sending: Mutex<()>,
+}
+
+/// The latest computation of `Signal::map_async` and the task running it.
+#[derive(Default)]
+struct Flight {
+    generation: usize,
+    waker: Option<Waker>,
+}
+
+impl Latest {
+    /// Starts a new computation and wakes the task running the one it
+    /// supersedes, returning the new generation.
+    fn supersede(&self) -> usize {
+        let mut flight = self.flight.lock();
+        flight.generation += 1;
+        let (generation, waker) = (flight.generation, flight.waker.take());
+        drop(flight);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        generation
+    }
+}
+
+/// Future of a `Signal::map_async` computation, resolving to `None` once a
+/// newer one has started.
+struct Cancellable<F> {
+    future: Pin<Box<F>>,
+    generation: usize,
+    latest: Arc<Latest>,
+}
+
+impl<F: Future> Future for Cancellable<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut flight = self.latest.flight.lock();
+        if flight.generation != self.generation {
+            return Poll::Ready(None);
+        }
+        flight.waker = Some(cx.waker().clone());
+        drop(flight);
+        self.future.as_mut().poll(cx).map(Some)
+    }
+}
+
+/// The value a future resolves to, and the task waiting for it.
+struct Slot<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+}
+
+impl<T> Slot<T> {
+    fn new() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Slot {
+            value: None,
+            waker: None,
+        }))
+    }
+
+    /// Stores the value and wakes the waiting task.
+    fn fill(&mut self, value: T) {
+        self.value = Some(value);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Takes the value, or remembers the task to wake once it arrives.
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<T> {
+        match self.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                self.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Future resolving to the next value a `sync::Signal` is updated to.
+///
+/// Created by `Signal::changed`. The future starts listening when it is
+/// created rather than when it is first polled, so an update sent in between
+/// is not missed. After the first update it stops listening.
+#[must_use = "futures do nothing unless polled"]
+pub struct Changed<T: 'static> {
+    slot: Arc<Mutex<Slot<T>>>,
+    /// Receiver of the source signal that fills the slot
+    _listener: Signal<T>,
+}
+
+impl<T: Clone + Send + Sync + 'static> Changed<T> {
+    pub(crate) fn new(source: &Signal<T>) -> Self {
+        let slot = Slot::new();
+        let (reader, source_, slot_) = (source.clone(), source.clone(), slot.clone());
+        let listener = Signal::driven(
+            move || reader.get(),
+            move |listener, value| {
+                // Updates racing in from other threads keep the first value.
+                let mut slot = slot_.lock();
+                if slot.value.is_none() {
+                    slot.fill(value.clone());
+                }
+                drop(slot);
+                source_.remove_receiver(listener);
+            },
+        );
+        source.add_receiver(listener.clone());
+        Self {
+            slot,
+            _listener: listener,
+        }
+    }
+}
+
+impl<T: 'static> Future for Changed<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        self.slot.lock().poll(cx)
+    }
+}
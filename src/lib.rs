@@ -1,9 +1,73 @@
-use api::{Receptive, SealedSignalTrait};
+#![cfg_attr(not(feature = "std"), no_std)]
 
-use std::{cell::RefCell, rc::Rc};
+extern crate alloc;
+
+use api::{Receptive, SealedSignalTrait, SignalTrait, State};
+
+use alloc::{boxed::Box, collections::VecDeque, rc::Rc, vec::Vec};
+use core::{
+    cell::{Cell, OnceCell, RefCell, RefMut},
+    mem,
+};
+
+#[cfg(feature = "std")]
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+};
+
+/// Implements the arithmetic operators on `&Signal` for the `Signal` in scope.
+///
+/// Each operator creates a derived signal combining the values of both
+/// operands. The traits passed in are extra bounds on the value types.
+macro_rules! impl_ops {
+    (@op $trait:ident $method:ident $($bound:ident),*) => {
+        impl<T, U> core::ops::$trait<&Signal<U>> for &Signal<T>
+        where
+            T: core::ops::$trait<U> + Clone $(+ $bound)* + 'static,
+            U: Clone $(+ $bound)* + 'static,
+            T::Output: 'static $(+ $bound)*,
+        {
+            type Output = Signal<T::Output>;
+
+            fn $method(self, rhs: &Signal<U>) -> Signal<T::Output> {
+                let (a, b) = (self.clone(), rhs.clone());
+                let signal = Signal::driven(
+                    move || core::ops::$trait::$method(a.get(), b.get()),
+                    |_, _| {},
+                );
+                self.add_receiver(signal.clone());
+                rhs.add_receiver(signal.clone());
+                signal
+            }
+        }
+    };
+    ($($bound:ident),*) => {
+        impl_ops!(@op Add add $($bound),*);
+        impl_ops!(@op Sub sub $($bound),*);
+        impl_ops!(@op Mul mul $($bound),*);
+        impl_ops!(@op Div div $($bound),*);
+        impl_ops!(@op Rem rem $($bound),*);
+    };
+}
 
 pub mod api;
+pub mod diff;
+#[cfg(feature = "futures")]
+pub mod future;
+#[cfg(feature = "std")]
+mod lock;
+#[cfg(feature = "futures")]
+pub mod stream;
+#[cfg(feature = "std")]
 pub mod sync;
+#[cfg(feature = "timer")]
+pub mod timer;
+
+pub use api::{
+    batch, is_deterministic, propagation_order, send_all, set_deterministic, set_propagation_order,
+    untrack, Batch, Order,
+};
 
 /// A reactive signal that can be observed and updated.
 ///
@@ -14,18 +78,18 @@ pub mod sync;
 /// # Usage
 ///
 /// ```rust
+/// # unsafe { reactivity::api::assume_single_context() }
 /// use reactivity::Signal;
 /// use reactivity::signal;
+/// use reactivity::api::SignalTrait;
 ///
 /// // Create a basic signal
 /// let count = signal!(0);
 ///
 /// // Create a derived signal
+/// // (the signal! macro registers it as a receiver of `count` automatically)
 /// let doubled = signal!([count] count * 2);
 ///
-/// // Manually establish dependency (the signal! macro does this automatically)
-/// count.add_receiver(doubled);
-///
 /// // Update the original signal
 /// count.send(5);
 ///
@@ -37,20 +101,51 @@ pub mod sync;
 ///
 /// Use `Signal` when all signals will be accessed from the same thread.
 /// If you need to share signals across multiple threads, use `sync::Signal` instead.
-#[derive(Clone)]
-pub struct Signal<T> {
-    /// The current value of the signal
-    inner: Rc<RefCell<T>>,
-    /// Optional effect function called when the signal is updated
-    effect: Option<Rc<dyn Fn(&Signal<T>, &T)>>,
-    /// Optional function that computes the signal's value
-    processor: Option<Rc<dyn Fn() -> T>>,
-    /// List of receivers that depend on this signal
-    receivers: Rc<RefCell<Vec<Box<dyn Receptive>>>>,
-    /// Counter tracking pending updates
-    dirty: Rc<RefCell<usize>>,
+///
+/// # Memory
+///
+/// The value is stored inline with the rest of the shared state, so creating
+/// a signal takes a single allocation whatever its type, and a `Signal<()>`
+/// used as a pure event costs no more than that. Zero-sized values are not
+/// special-cased, as there is no separate allocation left to avoid. A derived
+/// signal adds one for its processor if that captures anything, and each
+/// receiver added to a signal stores two boxed handles, one in the receiver
+/// list and one in the receiver's list of sources.
+pub struct Signal<T: 'static> {
+    /// State shared by all clones of the signal
+    state: Rc<State<Signal<T>>>,
+}
+
+// Implemented by hand, as deriving would require `T: Clone`.
+impl<T: 'static> Clone for Signal<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
 }
 
+/// A handle to a receiver registered with `SignalTrait::subscribe`.
+///
+/// Dropping the handle unregisters the receiver.
+pub type Subscription = api::Subscription<dyn Receptive>;
+
+/// A read-only view of a `Signal`, see `SignalTrait::read_only`.
+pub type ReadSignal<T> = api::ReadSignal<Signal<T>>;
+
+/// A write-only handle to a `Signal`, see `SignalTrait::split`.
+pub type WriteSignal<T> = api::WriteSignal<Signal<T>>;
+
+/// A handle to a effect, see `Signal::effect`.
+pub type EffectHandle = api::EffectHandle<Signal<()>>;
+
+/// A receiver running a function, see `FnReceiver::new`.
+pub type FnReceiver = api::FnReceiver<Signal<()>>;
+
+/// A collection of subscriptions and effects that detach together, see
+/// `api::ObserverScope`.
+pub type ObserverScope = api::ObserverScope<Signal<()>>;
+
 impl<T: 'static> Signal<T> {
     /// Creates a signal that depends on other signals.
     ///
@@ -60,206 +155,3347 @@ impl<T: 'static> Signal<T> {
     /// - `effect`: Side effect function called when the signal changes, receives both
     ///   the signal reference and the newly computed value
     ///
+    /// The effect runs before the new value is stored, so reading the signal
+    /// from inside it still gives the previous value. Use `driven_prev` to
+    /// receive that value explicitly.
+    ///
     /// # Example
     ///
     /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
     /// // Create a signal that reacts to changes in another signal
     /// let count = Signal::new(0);
+    /// let source = count.clone();
     /// let doubled = Signal::driven(
-    ///     || count.get() * 2,
+    ///     move || source.get() * 2,
     ///     |_, new_value| println!("Doubled value is now: {}", new_value)
     /// );
-    /// count.add_receiver(Box::new(doubled));
+    /// count.add_receiver(doubled);
     /// ```
     pub fn driven<F>(processor: F, effect: impl Fn(&Signal<T>, &T) + 'static) -> Self
     where
         F: Fn() -> T + 'static,
     {
-        Self::init(
-            Rc::new(RefCell::new(processor())),
-            Some(Rc::new(effect)),
-            Some(Rc::new(processor)),
-            Rc::new(RefCell::new(Vec::new())),
-            Rc::new(RefCell::new(0)),
-        )
+        let value = processor();
+        Self::derived(value, move |_| Some(processor()), effect)
     }
-}
-
-impl<T: 'static> SealedSignalTrait for Signal<T> {
-    type Inner = T;
-    type Rc<U: ?Sized> = Rc<U>;
-    type Ptr<U> = RefCell<U>;
-    type Effect = dyn Fn(&Signal<T>, &T);
-    type Processor = dyn Fn() -> T;
-    type Receiver = dyn Receptive;
 
-    fn init(
-        inner: Rc<RefCell<Self::Inner>>,
-        effect: Option<Rc<Self::Effect>>,
-        processor: Option<Rc<Self::Processor>>,
-        receivers: Rc<RefCell<Vec<Box<Self::Receiver>>>>,
-        dirty: Rc<RefCell<usize>>,
-    ) -> Self {
-        Self {
-            inner,
-            effect,
-            processor,
-            receivers,
-            dirty,
-        }
+    /// Creates a derived signal whose effect receives both the previous and
+    /// the new value.
+    ///
+    /// Works like `driven`, but the effect is called as
+    /// `effect(signal, old, new)`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(1);
+    /// let source = count.clone();
+    /// let delta = Signal::driven_prev(
+    ///     move || source.get(),
+    ///     |_, old, new| assert_eq!((*old, *new), (1, 3)),
+    /// );
+    /// count.add_receiver(delta.clone());
+    ///
+    /// count.send(3);
+    /// ```
+    pub fn driven_prev<F>(processor: F, effect: impl Fn(&Signal<T>, &T, &T) + 'static) -> Self
+    where
+        F: Fn() -> T + 'static,
+    {
+        Self::driven(processor, move |signal, new| {
+            effect(signal, &signal.borrow(), new)
+        })
     }
 
-    fn inner(&self) -> &Rc<RefCell<T>> {
-        &self.inner
+    /// Creates a derived signal that computes each value from its previous
+    /// one.
+    ///
+    /// The signal starts at `initial`, and each update of a dependency calls
+    /// the processor with the current value to get the new one, which suits
+    /// feedback such as smoothing without a separate accumulator. The
+    /// previous value is only borrowed while the processor runs and is
+    /// replaced once it returns, so the processor may read this signal too,
+    /// but must not send to it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let input = Signal::new(10.0);
+    /// let source = input.clone();
+    /// let smoothed = Signal::driven_stateful(
+    ///     input.get(),
+    ///     move |previous| 0.5 * source.get() + 0.5 * previous,
+    ///     |_, _| {},
+    /// );
+    /// input.add_receiver(smoothed.clone());
+    ///
+    /// input.send(20.0);
+    /// assert_eq!(smoothed.get(), 15.0);
+    /// input.send(20.0);
+    /// assert_eq!(smoothed.get(), 17.5);
+    /// ```
+    pub fn driven_stateful<F>(
+        initial: T,
+        processor: F,
+        effect: impl Fn(&Signal<T>, &T) + 'static,
+    ) -> Self
+    where
+        F: Fn(&T) -> T + 'static,
+    {
+        Self::derived(initial, move |previous| Some(processor(previous)), effect)
     }
 
-    fn effect(&self) -> Option<&Rc<Self::Effect>> {
-        self.effect.as_ref()
+    /// Creates a signal computed by `f` that depends on every signal `f`
+    /// reads.
+    ///
+    /// The dependencies are not listed as with `driven`: each signal read
+    /// with `get`, `value`, `with` or `borrow` while `f` runs is recorded,
+    /// and the new signal is added as a receiver of it, so a dependency
+    /// cannot be forgotten. The dependencies are recorded again on each run:
+    /// signals read for the first time are added, and signals that are no
+    /// longer read, such as those of a branch not taken, are dropped. Reads
+    /// with `peek` or inside `untrack` are not recorded, and neither are
+    /// reads of `sync::Signal`s.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let price = Signal::new(3);
+    /// let quantity = Signal::new(2);
+    /// let (price_, quantity_) = (price.clone(), quantity.clone());
+    /// let total = Signal::computed(move || price_.get() * quantity_.get());
+    ///
+    /// quantity.send(5);
+    /// assert_eq!(total.get(), 15);
+    /// price.send(4);
+    /// assert_eq!(total.get(), 20);
+    /// ```
+    pub fn computed(f: impl Fn() -> T + 'static) -> Self {
+        let this = Rc::new(OnceCell::<api::WeakSignal<Signal<T>>>::new());
+        let dependencies = Rc::new(api::Dependencies::default());
+        let (value, reads) = api::tracked(&f);
+        let (this_, dependencies_) = (this.clone(), dependencies.clone());
+        let signal = Self::derived(
+            value,
+            move |_| {
+                let (value, reads) = api::tracked(&f);
+                if let Some(signal) = this_.get().and_then(api::WeakSignal::upgrade) {
+                    dependencies_.update(&signal, reads);
+                }
+                Some(value)
+            },
+            |_, _| {},
+        );
+        let _ = this.set(signal.downgrade());
+        dependencies.update(&signal, reads);
+        signal
     }
 
-    fn processor(&self) -> Option<&Rc<Self::Processor>> {
-        self.processor.as_ref()
+    /// Creates a derived signal that computes its first value when it is
+    /// first needed.
+    ///
+    /// Works like `driven`, but the processor does not run until the value is
+    /// read, a receiver is added to the signal, or one of its dependencies is
+    /// updated. A signal that is never used never computes. The dependencies
+    /// captured by the processor must therefore still be alive at that point.
+    /// Until then the signal holds `T::default()`, which is never seen
+    /// through it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use std::{cell::Cell, rc::Rc};
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let runs = Rc::new(Cell::new(0));
+    /// let count = Signal::new(2);
+    /// let (source, runs_) = (count.clone(), runs.clone());
+    /// let doubled = Signal::driven_lazy(
+    ///     move || {
+    ///         runs_.set(runs_.get() + 1);
+    ///         source.get() * 2
+    ///     },
+    ///     |_, _| {},
+    /// );
+    /// count.add_receiver(doubled.clone());
+    /// assert_eq!(runs.get(), 0);
+    ///
+    /// assert_eq!(doubled.get(), 4);
+    /// assert_eq!(runs.get(), 1);
+    /// ```
+    pub fn driven_lazy<F>(processor: F, effect: impl Fn(&Signal<T>, &T) + 'static) -> Self
+    where
+        T: Default,
+        F: Fn() -> T + 'static,
+    {
+        let signal = Self::derived(T::default(), move |_| Some(processor()), effect);
+        *signal.lazy().borrow_mut() = true;
+        signal
     }
 
-    fn receivers(&self) -> &Rc<RefCell<Vec<Box<Self::Receiver>>>> {
-        &self.receivers
+    /// Creates a derived signal whose effect also runs for the initial value.
+    ///
+    /// `driven` computes the initial value without running the effect, which
+    /// suits derived values but not watchers that should act on the current
+    /// state right away. This constructor runs the effect once as soon as the
+    /// signal is created. On that first run the stored value is already the
+    /// new one, so the `before` and `after` values of a `signal!` effect are
+    /// equal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(1);
+    /// let source = count.clone();
+    /// // Prints "count is 1" right away
+    /// let _watcher = Signal::driven_immediate(
+    ///     move || source.get(),
+    ///     |_, count| println!("count is {}", count),
+    /// );
+    /// ```
+    pub fn driven_immediate<F>(processor: F, effect: impl Fn(&Signal<T>, &T) + 'static) -> Self
+    where
+        F: Fn() -> T + 'static,
+    {
+        let signal = Self::driven(processor, effect);
+        if let Some(effect) = signal.effect() {
+            // No one else holds the signal yet, so the effect may read it
+            // while the value is borrowed.
+            effect(&signal, &signal.borrow());
+        }
+        signal
     }
 
-    fn dirty(&self) -> &Rc<RefCell<usize>> {
-        &self.dirty
+    /// Creates a derived signal that only reacts when its value changes.
+    ///
+    /// Works like `driven`, but each recomputed value is compared with the stored
+    /// one. If they are equal, the effect is skipped and receivers are not
+    /// notified.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(1);
+    /// let source = count.clone();
+    /// let parity = Signal::driven_eq(
+    ///     move || source.get() % 2,
+    ///     |_, parity| println!("Parity changed to {}", parity)
+    /// );
+    /// count.add_receiver(parity.clone());
+    ///
+    /// // Prints nothing, the parity is still 1
+    /// count.send(3);
+    /// ```
+    pub fn driven_eq<F>(processor: F, effect: impl Fn(&Signal<T>, &T) + 'static) -> Self
+    where
+        F: Fn() -> T + 'static,
+        T: PartialEq,
+    {
+        Self::driven_with_eq(processor, effect, T::eq)
     }
-}
-
-/// A macro to create reactive signals.
-///
-/// This macro supports creating both single-threaded signals (`reactivity::Signal`)
-/// and thread-safe signals (`reactivity::sync::Signal`) depending on the context.
-///
-/// # Syntax
-///
-/// ```
-/// // Create a basic signal with a value
-/// signal!(value)
-///
-/// // Create a signal that reacts to other signals
-/// signal!([dep1, dep2, ...] expression)
-///
-/// // Create a signal with custom effect function
-/// signal!(<before, after> [dep1, dep2, ...] expression; effect_code)
-/// ```
-///
-/// # Examples
-///
-/// ## Single-threaded usage
-///
-/// ```rust
-/// use reactivity::Signal;
-/// let x = signal!(1);
-/// let y = signal!([x] x * 2);
-///
-/// x.send(5);
-/// assert_eq!(y.get(), 10);
-/// ```
-///
-/// ## Thread-safe usage
-///
-/// ```rust
-/// use std::thread;
-/// use reactivity::sync::Signal;
-///
-/// let x = signal!(1);
-/// let y = signal!([x] x * 2);
-/// let x_clone = x.clone();
-///
-/// thread::spawn(move || {
-///     x_clone.send(5);
-/// }).join().unwrap();
-///
-/// assert_eq!(y.get(), 10);
-/// ```
-///
-/// # Choosing Between Signal Types
-///
-/// - Use `reactivity::Signal` (imported with `use reactivity::Signal`) for single-threaded contexts
-/// - Use `reactivity::sync::Signal` (imported with `use reactivity::sync::Signal`) for multi-threaded contexts
-///
-/// The `signal!` macro will use the correct Signal implementation based on your imports.
-#[macro_export]
-macro_rules! signal {
-    ($(< $_before:ident $(, $_after:ident)? >)? [$($params:ident),*] $proc:expr) => {
-        signal!($(<$_before:ident $(, $_after:ident)?>)? [$($params),*] $proc; ())
-    };
-    ($(< $_before:ident $(, $_after:ident)? >)? [$($params:ident),*] $proc:expr; $eff:expr) => {
-        {
-            use $crate::api::SignalTrait;
-            $(
-                let $params = $params.clone();
-                paste::paste!{ let [<$params _>] = $params.clone(); }
-                paste::paste!{ let [<$params __>] = $params.clone(); }
-            )*
-            let processor = move || {
-                $(
-                    let $params = $params.get();
-                )*
-                $proc
-            };
-            let signal = Signal::driven(processor, move |_self, _after| {
-                $(
-                    let $_before = _self.get();
-                    $(
-                        let $_after = _after.clone();
-                    )?
-                )?
-                $(
-                    paste::paste!{
-                        #[allow(unused_variables)]
-                        let $params = [<$params _>].clone();
-                    }
-                )*
-                $eff
-            });
 
-            $(
-                paste::paste!{
-                    let signal_ = signal.clone();
-                    [<$params __>].add_receiver(signal_);
-                }
-            )*
+    /// Creates a derived signal that only reacts when `eq` considers its value
+    /// changed.
+    ///
+    /// Works like `driven_eq`, but `eq(old, new)` decides whether a recomputed
+    /// value is the same as the stored one. If it returns `true`, the stored
+    /// value is kept, the effect is skipped and receivers are not notified.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1.0_f64);
+    /// let source = reading.clone();
+    /// let smoothed = Signal::driven_with_eq(
+    ///     move || source.get(),
+    ///     |_, _| {},
+    ///     |old, new| (old - new).abs() < 0.1,
+    /// );
+    /// reading.add_receiver(smoothed.clone());
+    ///
+    /// reading.send(1.05);
+    /// assert_eq!(smoothed.get(), 1.0);
+    /// reading.send(1.5);
+    /// assert_eq!(smoothed.get(), 1.5);
+    /// ```
+    pub fn driven_with_eq<F>(
+        processor: F,
+        effect: impl Fn(&Signal<T>, &T) + 'static,
+        eq: impl Fn(&T, &T) -> bool + 'static,
+    ) -> Self
+    where
+        F: Fn() -> T + 'static,
+    {
+        let value = processor();
+        Self::derived(
+            value,
+            move |current| {
+                let value = processor();
+                (!eq(current, &value)).then_some(value)
+            },
+            effect,
+        )
+    }
 
-            signal
-        }
-    };
+    /// Creates a signal with an initial value that recomputes with `processor`.
+    ///
+    /// The processor receives the current value and returns `None` to keep it
+    /// without running the effect or notifying receivers.
+    fn derived(
+        value: T,
+        processor: impl Fn(&T) -> Option<T> + 'static,
+        effect: impl Fn(&Signal<T>, &T) + 'static,
+    ) -> Self {
+        Self::init(value, Some(Box::new(effect)), Some(Box::new(processor)))
+    }
 
-    ($value:expr) => {
-        {
-            use $crate::api::SignalTrait;
-            Signal::new($value)
-        }
-    };
-}
+    /// Creates a derived signal that applies `f` to each value of this signal.
+    ///
+    /// The new signal is registered as a receiver of this one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(2);
+    /// let label = count.map(|count| format!("{} items", count));
+    ///
+    /// count.send(3);
+    /// assert_eq!(label.get(), "3 items");
+    /// ```
+    pub fn map<U: 'static, F>(&self, f: F) -> Signal<U>
+    where
+        F: Fn(&T) -> U + 'static,
+    {
+        let source = self.clone();
+        let signal = Signal::driven(move || f(&source.borrow()), |_, _| {});
+        self.add_receiver(signal.clone());
+        signal
+    }
 
-#[cfg(test)]
-mod tests {
-    use std::thread;
+    /// Creates a derived signal holding the part of each value picked by `f`,
+    /// passed on only when that part changes.
+    ///
+    /// `f` reads the value by reference, so the value of this signal is never
+    /// cloned, and the new signal compares each result with the last one, so
+    /// changes elsewhere in the value do not reach its receivers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// struct Config {
+    ///     log_level: u8,
+    ///     title: String,
+    /// }
+    ///
+    /// let config = Signal::new(Config { log_level: 1, title: "app".into() });
+    /// let log_level = config.project(|config| config.log_level);
+    ///
+    /// config.update(|config| config.title.push('!'));
+    /// assert_eq!(log_level.update_count(), 0);
+    ///
+    /// config.update(|config| config.log_level = 3);
+    /// assert_eq!(log_level.get(), 3);
+    /// ```
+    pub fn project<U, F>(&self, f: F) -> Signal<U>
+    where
+        U: PartialEq + 'static,
+        F: Fn(&T) -> U + 'static,
+    {
+        let source = self.clone();
+        let signal = Signal::derived(
+            self.with(&f),
+            move |last| {
+                let value = source.with(&f);
+                (value != *last).then_some(value)
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
 
-    use crate::{api::SignalTrait, sync::Signal};
+    /// Creates a derived signal converting each value of this signal with
+    /// `U::from`, see `map`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let small = Signal::new(7_u32);
+    /// let wide = small.coerce::<u64>();
+    ///
+    /// small.send(u32::MAX);
+    /// assert_eq!(wide.get(), u64::from(u32::MAX));
+    /// ```
+    pub fn coerce<U: From<T> + 'static>(&self) -> Signal<U>
+    where
+        T: Clone,
+    {
+        self.map(|value| U::from(value.clone()))
+    }
 
-    #[test]
-    fn test() {
-        // Diamond dependency
-        let x = signal!(1);
-        let doubled_x = signal!([x] x * 2);
+    /// Creates a derived signal forwarding each value of this signal.
+    ///
+    /// The copy is a separate branch point: adapters such as `dedup` can be
+    /// chained on it without changing what other receivers of this signal
+    /// see.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1);
+    /// let changes = reading.cloned().dedup();
+    ///
+    /// reading.send(2);
+    /// reading.send(2);
+    /// assert_eq!(reading.update_count(), 2);
+    /// assert_eq!(changes.update_count(), 1);
+    /// ```
+    pub fn cloned(&self) -> Signal<T>
+    where
+        T: Clone,
+    {
+        self.map(T::clone)
+    }
+
+    /// Creates a derived signal forwarding each value of this signal
+    /// unchanged, calling `f` on each one first.
+    ///
+    /// `f` runs for every update of this signal, not for the current value,
+    /// which makes it a probe for logging or metrics in the middle of a chain.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1);
+    /// let doubled = reading
+    ///     .inspect(|reading| println!("reading: {reading}"))
+    ///     .map(|reading| reading * 2);
+    ///
+    /// reading.send(4);
+    /// assert_eq!(doubled.get(), 8);
+    /// ```
+    pub fn inspect(&self, f: impl Fn(&T) + 'static) -> Signal<T>
+    where
+        T: Clone,
+    {
+        let source = self.clone();
+        let signal = Signal::derived(
+            self.get(),
+            move |_| Some(source.get()),
+            move |_, value| f(value),
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Applies a transform built from other combinators to this signal.
+    ///
+    /// This is `f(self)`, written so that a pipeline kept in a function can
+    /// be applied to several sources and sit in the middle of a chain.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// fn positive_changes(signal: &Signal<i32>) -> Signal<i32> {
+    ///     signal.filter(|value| *value > 0).dedup()
+    /// }
+    ///
+    /// let reading = Signal::new(1);
+    /// let labels = reading
+    ///     .pipe(positive_changes)
+    ///     .map(|value| format!("{value} items"));
+    ///
+    /// reading.send(-2);
+    /// reading.send(3);
+    /// assert_eq!(labels.get(), "3 items");
+    /// ```
+    pub fn pipe<U: 'static>(&self, f: impl FnOnce(&Self) -> Signal<U>) -> Signal<U> {
+        f(self)
+    }
+
+    /// Creates a derived signal holding the values of both `a` and `b`.
+    ///
+    /// The new signal is registered as a receiver of both sources. When both
+    /// are updated in the same batch, it recomputes only once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::{api::SignalTrait, batch};
+    ///
+    /// let name = Signal::new("apples");
+    /// let count = Signal::new(2);
+    /// let both = Signal::combine(&name, &count);
+    ///
+    /// batch(|| {
+    ///     name.send("pears");
+    ///     count.send(3);
+    /// });
+    /// assert_eq!(both.get(), ("pears", 3));
+    /// ```
+    pub fn combine<U>(a: &Signal<T>, b: &Signal<U>) -> Signal<(T, U)>
+    where
+        T: Clone,
+        U: Clone + 'static,
+    {
+        let (a_, b_) = (a.clone(), b.clone());
+        let signal = Signal::driven(move || (a_.get(), b_.get()), |_, _| {});
+        a.add_receiver(signal.clone());
+        b.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Combines this signal with `other`, see `Signal::combine`.
+    pub fn zip<U>(&self, other: &Signal<U>) -> Signal<(T, U)>
+    where
+        T: Clone,
+        U: Clone + 'static,
+    {
+        Signal::combine(self, other)
+    }
+
+    /// Creates a derived signal holding the values of all `signals`, in order.
+    ///
+    /// The new signal is registered as a receiver of each source and
+    /// recomputes the whole list when any of them changes, once per batch
+    /// like `combine`. With no signals it holds an empty list for good.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let sensors: Vec<_> = (0..3).map(|_| Signal::new(0.0)).collect();
+    /// let readings = Signal::combine_all(&sensors);
+    ///
+    /// sensors[1].send(2.5);
+    /// assert_eq!(readings.get(), vec![0.0, 2.5, 0.0]);
+    /// ```
+    pub fn combine_all(signals: &[Signal<T>]) -> Signal<Vec<T>>
+    where
+        T: Clone,
+    {
+        let sources = signals.to_vec();
+        let signal = Signal::driven(move || sources.iter().map(Signal::get).collect(), |_, _| {});
+        for source in signals {
+            source.add_receiver(signal.clone());
+        }
+        signal
+    }
+
+    /// Creates a derived signal that only takes values satisfying `predicate`.
+    ///
+    /// When the source changes to a value the predicate rejects, the filtered
+    /// signal keeps its last accepted value and its receivers are not notified.
+    /// It starts with the source's current value, whether or not it passes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(0);
+    /// let high = reading.filter(|reading| *reading > 100);
+    ///
+    /// reading.send(120);
+    /// reading.send(80);
+    /// assert_eq!(high.get(), 120);
+    /// ```
+    pub fn filter<F>(&self, predicate: F) -> Signal<T>
+    where
+        T: Clone,
+        F: Fn(&T) -> bool + 'static,
+    {
+        let source = self.clone();
+        let signal = Signal::derived(
+            self.get(),
+            move |_| {
+                let value = source.borrow();
+                predicate(&value).then(|| value.clone())
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal that skips consecutive duplicate values.
+    ///
+    /// Each value of this signal is compared with the last one forwarded, and
+    /// only a different value is taken and passed on to receivers. Unlike
+    /// `driven_eq`, this works on any signal, however it was built. The first
+    /// update after creation is always forwarded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1);
+    /// let changes = reading.dedup();
+    ///
+    /// reading.send(1);
+    /// reading.send(2);
+    /// reading.send(2);
+    /// assert_eq!(changes.update_count(), 2);
+    /// ```
+    pub fn dedup(&self) -> Signal<T>
+    where
+        T: Clone + PartialEq,
+    {
+        let source = self.clone();
+        let first = Cell::new(true);
+        let signal = Signal::derived(
+            self.get(),
+            move |last| {
+                let value = source.borrow();
+                (first.replace(false) || *value != *last).then(|| value.clone())
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal that takes only every `n`-th update of this
+    /// one.
+    ///
+    /// The new signal starts at the current value, which does not count
+    /// towards the stride: it takes the values of the `n`-th, `2n`-th, and so
+    /// on update after its creation and keeps its value in between.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let frame = Signal::new(0);
+    /// let sampled = frame.every(3);
+    ///
+    /// (1..=7).for_each(|n| {
+    ///     frame.send(n);
+    /// });
+    /// assert_eq!(sampled.get(), 6);
+    /// assert_eq!(sampled.update_count(), 2);
+    /// ```
+    pub fn every(&self, n: usize) -> Signal<T>
+    where
+        T: Clone,
+    {
+        assert!(n > 0, "`every` needs a stride of at least one");
+        let source = self.clone();
+        let seen = Cell::new(0_usize);
+        let signal = Signal::derived(
+            self.get(),
+            move |_| {
+                let count = seen.get() + 1;
+                seen.set(count);
+                count.is_multiple_of(n).then(|| source.get())
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal holding the last `size` values of this one,
+    /// oldest first.
+    ///
+    /// The window starts with the current value and takes each update,
+    /// dropping the oldest value once it holds `size` of them, so it is
+    /// partial until enough values have arrived.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1);
+    /// let recent = reading.window(3);
+    /// let average = recent.map(|values| values.iter().sum::<i32>() / values.len() as i32);
+    ///
+    /// reading.send(5);
+    /// assert_eq!(recent.get(), [1, 5]);
+    /// (6..=8).for_each(|n| {
+    ///     reading.send(n);
+    /// });
+    /// assert_eq!(recent.get(), [6, 7, 8]);
+    /// assert_eq!(average.get(), 7);
+    /// ```
+    pub fn window(&self, size: usize) -> Signal<VecDeque<T>>
+    where
+        T: Clone,
+    {
+        assert!(size > 0, "`window` needs a size of at least one");
+        let source = self.clone();
+        let signal = Signal::derived(
+            VecDeque::from([self.get()]),
+            move |window| {
+                let mut window = window.clone();
+                if window.len() == size {
+                    window.pop_front();
+                }
+                window.push_back(source.get());
+                Some(window)
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal that follows this one only while `enabled` is
+    /// true.
+    ///
+    /// The new signal starts at the current value and is registered as a
+    /// receiver of both signals. Updates arriving while `enabled` is false are
+    /// not taken, and when it flips back to true the signal catches up with
+    /// the current value of this one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let input = Signal::new(1);
+    /// let live = Signal::new(true);
+    /// let preview = input.gated_by(&live);
+    ///
+    /// live.send(false);
+    /// input.send(2);
+    /// assert_eq!(preview.get(), 1);
+    ///
+    /// live.send(true);
+    /// assert_eq!(preview.get(), 2);
+    /// ```
+    pub fn gated_by(&self, enabled: &Signal<bool>) -> Signal<T>
+    where
+        T: Clone,
+    {
+        let (source, enabled_) = (self.clone(), enabled.clone());
+        let signal = Signal::derived(
+            self.get(),
+            move |_| enabled_.get().then(|| source.get()),
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        enabled.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a signal taking the value of whichever of `signals` updated
+    /// last.
+    ///
+    /// The new signal starts at the value of the first signal. Each of
+    /// `signals` gets a listener that sends its new values on to the merged
+    /// signal, owned by that signal so it lasts as long as the source does.
+    /// When several sources update in one propagation, for example inside a
+    /// `batch`, each value is sent on in turn, and the source whose listener
+    /// runs last in propagation order wins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `signals` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let keyboard = Signal::new("none");
+    /// let mouse = Signal::new("none");
+    /// let last_input = Signal::merge(&[keyboard.clone(), mouse.clone()]);
+    ///
+    /// mouse.send("click");
+    /// assert_eq!(last_input.get(), "click");
+    /// keyboard.send("enter");
+    /// assert_eq!(last_input.get(), "enter");
+    /// ```
+    pub fn merge(signals: &[Signal<T>]) -> Signal<T>
+    where
+        T: Clone,
+    {
+        let first = signals.first().expect("`merge` needs at least one signal");
+        let merged = Signal::new(first.get());
+        for signal in signals {
+            let (source, target) = (signal.downgrade(), merged.downgrade());
+            let listener = Signal::derived(
+                (),
+                |_| Some(()),
+                move |_, _| {
+                    if let (Some(source), Some(target)) = (source.upgrade(), target.upgrade()) {
+                        drop(target.send(source.get()));
+                    }
+                },
+            );
+            let listener: Box<dyn Receptive> = Box::new(listener);
+            signal.add_receiver(listener);
+        }
+        merged
+    }
+
+    /// Keeps `a` and `b` in sync in both directions.
+    ///
+    /// `b` first takes `to` of the value of `a`. After that each update of
+    /// `a` sends `to` of its value to `b`, and each update of `b` sends `from`
+    /// of its value to `a`. A forwarded value equal to the current one is not
+    /// sent, which ends the exchange, so `from` and `to` have to agree:
+    /// `to(&from(&to(&x)))` must equal `to(&x)`, or the two keep updating
+    /// each other until `api::max_depth` rounds. The listeners are owned by
+    /// the signals and last as long as both do.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let celsius = Signal::new(100.0);
+    /// let fahrenheit = Signal::new(0.0);
+    /// Signal::bind(&celsius, &fahrenheit, |c| c * 9.0 / 5.0 + 32.0, |f| (f - 32.0) * 5.0 / 9.0);
+    /// assert_eq!(fahrenheit.get(), 212.0);
+    ///
+    /// fahrenheit.send(32.0);
+    /// assert_eq!(celsius.get(), 0.0);
+    /// ```
+    pub fn bind<U>(
+        a: &Signal<T>,
+        b: &Signal<U>,
+        to: impl Fn(&T) -> U + 'static,
+        from: impl Fn(&U) -> T + 'static,
+    ) where
+        T: PartialEq,
+        U: PartialEq + 'static,
+    {
+        b.set_if_changed(a.with(&to));
+        let (source, target) = (a.downgrade(), b.downgrade());
+        let forward = Signal::derived(
+            (),
+            |_| Some(()),
+            move |_, _| {
+                if let (Some(source), Some(target)) = (source.upgrade(), target.upgrade()) {
+                    target.set_if_changed(source.with(&to));
+                }
+            },
+        );
+        let (source, target) = (b.downgrade(), a.downgrade());
+        let backward = Signal::derived(
+            (),
+            |_| Some(()),
+            move |_, _| {
+                if let (Some(source), Some(target)) = (source.upgrade(), target.upgrade()) {
+                    target.set_if_changed(source.with(&from));
+                }
+            },
+        );
+        let forward: Box<dyn Receptive> = Box::new(forward);
+        let backward: Box<dyn Receptive> = Box::new(backward);
+        a.add_receiver(forward);
+        b.add_receiver(backward);
+    }
+
+    /// Creates a signal taking the value of `value` each time `trigger` updates.
+    ///
+    /// Only `trigger` gets the new signal as a receiver. `value` is peeked when
+    /// the trigger fires, so its own changes are not passed on until then.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let clicks = Signal::new(0);
+    /// let text = Signal::new("draft");
+    /// let submitted = Signal::sample(&clicks, &text);
+    ///
+    /// text.send("final");
+    /// assert_eq!(submitted.get(), "draft");
+    ///
+    /// clicks.send(1);
+    /// assert_eq!(submitted.get(), "final");
+    /// ```
+    pub fn sample<U>(trigger: &Signal<U>, value: &Signal<T>) -> Signal<T>
+    where
+        T: Clone,
+        U: 'static,
+    {
+        let value = value.clone();
+        let signal = Signal::driven(move || value.peek(), |_, _| {});
+        trigger.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a signal that keeps the latest `Some` value of `source`.
+    ///
+    /// The new signal starts at `initial` and is registered as a receiver of
+    /// `source`. Each time `source` updates to `Some(value)` it takes `value`,
+    /// while updates to `None` are ignored. If `source` already holds a value
+    /// when `hold` is called, the signal starts at that value and `initial` is
+    /// never used. Mapping an `Async` signal with `Async::ready` gives a
+    /// placeholder until a future completes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let clicked = Signal::new(None);
+    /// let last = Signal::hold("nothing yet", &clicked);
+    /// assert_eq!(last.get(), "nothing yet");
+    ///
+    /// clicked.send(Some("ok"));
+    /// clicked.send(None);
+    /// assert_eq!(last.get(), "ok");
+    /// ```
+    pub fn hold(initial: T, source: &Signal<Option<T>>) -> Signal<T>
+    where
+        T: Clone,
+    {
+        let value = source.peek().unwrap_or(initial);
+        let source_ = source.clone();
+        let signal = Signal::derived(value, move |_| source_.peek(), |_, _| {});
+        source.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Converts this signal into a thread-safe `sync::Signal`.
+    ///
+    /// Only the current value carries over into a fresh signal. Processors,
+    /// effects and receivers cannot move between threading models, so a derived
+    /// signal stops being derived and the new signal has no receivers. Rebuild
+    /// the dependencies on the new signal where they are still needed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(3);
+    /// let shared = count.into_sync();
+    ///
+    /// std::thread::spawn(move || assert_eq!(shared.get(), 3))
+    ///     .join()
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn into_sync(self) -> sync::Signal<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        sync::Signal::new(self.get())
+    }
+
+    /// Sends a value unless the value of this signal is borrowed.
+    ///
+    /// `send` panics when called while the value is borrowed, for example
+    /// from inside `with` or an effect that still holds a guard from
+    /// `borrow`. This hands `value` back instead and propagates nothing. The
+    /// update has settled by the time this returns. A constant ignores the
+    /// value, as with `send`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(1);
+    /// count.with(|value| {
+    ///     assert_eq!(count.try_send(value + 1), Err(2));
+    /// });
+    ///
+    /// assert_eq!(count.try_send(2), Ok(()));
+    /// assert_eq!(count.get(), 2);
+    /// ```
+    pub fn try_send(&self, value: T) -> Result<(), T> {
+        if self.is_constant() {
+            return Ok(());
+        }
+        let Ok(mut inner) = self.inner().try_borrow_mut() else {
+            return Err(value);
+        };
+        let old = mem::replace(&mut *inner, value);
+        self.stored(&inner);
+        drop(inner);
+        drop(old);
+        drop(api::propagate(self));
+        Ok(())
+    }
+
+    /// Calls `f` with the previous and the new value on each update of this
+    /// signal.
+    ///
+    /// `f` is not called for the current value. On the first update it
+    /// receives the value this signal had when `watch` was called as the
+    /// previous one, and after that the value of the update before. The
+    /// listener is owned by this signal and removed when the returned
+    /// subscription is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let temperature = Signal::new(20);
+    /// let _watch = temperature.watch(|old, new| println!("{old} -> {new}"));
+    ///
+    /// // Prints "20 -> 23"
+    /// temperature.send(23);
+    /// ```
+    pub fn watch(&self, f: impl Fn(&T, &T) + 'static) -> Subscription
+    where
+        T: Clone,
+    {
+        let last = RefCell::new(self.get());
+        let source = self.downgrade();
+        let listener = Signal::derived(
+            (),
+            |_| Some(()),
+            move |_, _| {
+                if let Some(source) = source.upgrade() {
+                    let new = source.get();
+                    let old = last.replace(new.clone());
+                    f(&old, &new);
+                }
+            },
+        );
+        let listener: Box<dyn Receptive> = Box::new(listener);
+        self.subscribe(listener)
+    }
+
+    /// Runs `f` on each update of this signal until the returned subscription
+    /// is dropped.
+    ///
+    /// This is a `FnReceiver` added with `subscribe`, so the signal owns the
+    /// listener and nothing else has to be kept alive.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(0);
+    /// let changed = count.on_change(|| println!("count changed"));
+    ///
+    /// // Prints "count changed"
+    /// count.send(1);
+    /// drop(changed);
+    /// assert_eq!(count.receiver_count(), 0);
+    /// ```
+    pub fn on_change(&self, f: impl Fn() + 'static) -> Subscription {
+        self.subscribe(FnReceiver::new(f))
+    }
+
+    /// Runs `f` with the new value on each update of this signal until the
+    /// returned subscription is dropped.
+    ///
+    /// Receivers are only told that a source changed, so the listener keeps
+    /// a clone of each value as it is stored and passes those to `f`. Every
+    /// value sent reaches `f` once, in order, even one sent while the signal
+    /// is paused or from the effect of a receiver, and `f` may read or send
+    /// to this signal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let path = Signal::new(String::from("/"));
+    /// let _route = path.on_value(|path| println!("navigated to {path}"));
+    ///
+    /// // Prints "navigated to /settings"
+    /// path.send("/settings".into());
+    /// ```
+    pub fn on_value(&self, f: impl Fn(&T) + 'static) -> Subscription
+    where
+        T: Clone,
+    {
+        let snapshots = Rc::new(RefCell::new((VecDeque::new(), false)));
+        let queue = Rc::downgrade(&snapshots);
+        self.hooks()
+            .borrow_mut()
+            .push(Box::new(move |value: &T| match queue.upgrade() {
+                Some(queue) => {
+                    queue.borrow_mut().0.push_back(value.clone());
+                    true
+                }
+                None => false,
+            }));
+        self.on_change(move || api::deliver(&*snapshots, &f))
+    }
+
+    /// Runs `f` with the next value of this signal, then stops listening.
+    ///
+    /// The listener removes itself from the receivers once `f` has run. The
+    /// update in flight keeps working from its own snapshot of the receivers,
+    /// so siblings are not skipped. The returned handle keeps the listener
+    /// alive; dropping it before the next update cancels the call.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let ready = Signal::new(false);
+    /// let _once = ready.subscribe_once(|ready| println!("ready: {}", ready));
+    ///
+    /// // Prints "ready: true"
+    /// ready.send(true);
+    /// // Prints nothing
+    /// ready.send(false);
+    /// assert_eq!(ready.receiver_count(), 0);
+    /// ```
+    pub fn subscribe_once(&self, f: impl FnOnce(&T) + 'static) -> EffectHandle {
+        let f = RefCell::new(Some(f));
+        let source = self.downgrade();
+        let listener = Signal::derived(
+            (),
+            |_| Some(()),
+            move |listener, _| {
+                let Some(source) = source.upgrade() else {
+                    return;
+                };
+                if let Some(f) = f.borrow_mut().take() {
+                    source.with(f);
+                }
+                source.remove_receiver(listener);
+            },
+        );
+        self.add_receiver(listener.clone());
+        EffectHandle::new(listener)
+    }
+
+    /// Runs `f` once this signal is dropped.
+    ///
+    /// Clones of a signal share its state, so `f` runs when the last clone is
+    /// dropped, not when this handle is. Receiver lists do not keep the signal
+    /// alive, but the processors of signals derived from it usually hold
+    /// clones. Functions run in the order they were registered, after the
+    /// signal has detached from its sources.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let buffer = Signal::new(vec![0u8; 1024]);
+    /// buffer.on_drop(|| println!("buffer released"));
+    ///
+    /// let clone = buffer.clone();
+    /// drop(buffer);
+    /// // Prints "buffer released"
+    /// drop(clone);
+    /// ```
+    pub fn on_drop(&self, f: impl FnOnce() + 'static) {
+        self.cleanups().borrow_mut().push(Box::new(f));
+    }
+
+    /// Records each value this signal is updated to, for assertions in tests.
+    ///
+    /// The returned vec starts empty and gets a clone of every new value once
+    /// it is stored. The listener doing so is owned by this signal, so values
+    /// are recorded for as long as the signal lives. Only available with the
+    /// `testing` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::{signal, Signal};
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = signal!(0);
+    /// let doubled = signal!([count] count * 2);
+    /// let history = doubled.collect();
+    ///
+    /// count.send(1);
+    /// count.send(2);
+    /// assert_eq!(*history.borrow(), vec![2, 4]);
+    /// ```
+    #[cfg(feature = "testing")]
+    pub fn collect(&self) -> Rc<RefCell<Vec<T>>>
+    where
+        T: Clone,
+    {
+        let history = Rc::new(RefCell::new(Vec::new()));
+        let (source, values) = (self.downgrade(), history.clone());
+        let listener = Signal::derived(
+            (),
+            |_| Some(()),
+            move |_, _| {
+                if let Some(source) = source.upgrade() {
+                    values.borrow_mut().push(source.get());
+                }
+            },
+        );
+        let listener: Box<dyn Receptive> = Box::new(listener);
+        self.add_receiver(listener);
+        history
+    }
+
+    /// Creates a signal that steps through `values`, for deterministic tests.
+    ///
+    /// The signal starts with the first value. Each call of the returned
+    /// stepper sends the next one and returns `true`, or returns `false` once
+    /// every value has been sent. Only available with the `testing` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::{signal, Signal};
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let (reading, mut step) = Signal::replay(vec![1, 5, 3]);
+    /// let peak = signal!([reading] reading > 4);
+    ///
+    /// assert!(step());
+    /// assert!(peak.get());
+    /// assert!(step());
+    /// assert!(!step());
+    /// assert_eq!(reading.get(), 3);
+    /// ```
+    #[cfg(feature = "testing")]
+    pub fn replay(values: Vec<T>) -> (Signal<T>, impl FnMut() -> bool) {
+        let mut values = values.into_iter();
+        let signal = Signal::new(values.next().expect("`replay` needs at least one value"));
+        let source = signal.clone();
+        let step = move || match values.next() {
+            Some(value) => {
+                source.send(value);
+                true
+            }
+            None => false,
+        };
+        (signal, step)
+    }
+}
+
+impl<T: 'static> ReadSignal<T> {
+    /// Creates a derived signal that applies `f` to each value, see
+    /// `Signal::map`.
+    pub fn map<U: 'static, F>(&self, f: F) -> Signal<U>
+    where
+        F: Fn(&T) -> U + 'static,
+    {
+        self.signal.map(f)
+    }
+}
+
+impl Signal<()> {
+    /// Creates an effect that runs `f` each time one of its dependencies
+    /// changes.
+    ///
+    /// Unlike a derived signal, the effect holds no value and `f` is not run
+    /// on creation. Register `EffectHandle::observer` as a receiver of each
+    /// dependency, or use the `effect!` macro to do both at once. The effect
+    /// lives until the handle is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(0);
+    /// let source = count.clone();
+    /// let handle = Signal::effect(move || println!("count is {}", source.get()));
+    /// count.add_receiver(handle.observer().clone());
+    ///
+    /// // Prints "count is 1"
+    /// count.send(1);
+    ///
+    /// // Prints nothing, the effect is detached
+    /// drop(handle);
+    /// count.send(2);
+    /// ```
+    pub fn effect(f: impl Fn() + 'static) -> EffectHandle {
+        EffectHandle::new(Signal::derived(
+            (),
+            move |_| {
+                f();
+                Some(())
+            },
+            |_, _| {},
+        ))
+    }
+}
+
+impl ObserverScope {
+    /// Calls `f` with the previous and the new value on each update of
+    /// `signal` until the scope is dropped, see `Signal::watch`.
+    pub fn watch<T: Clone + 'static>(&mut self, signal: &Signal<T>, f: impl Fn(&T, &T) + 'static) {
+        self.add(signal.watch(f));
+    }
+}
+
+impl FnReceiver {
+    /// Wraps `f` as a receiver that runs it on each update of the signals it
+    /// is added to.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::{FnReceiver, Signal};
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(0);
+    /// // Prints "count changed" on each update
+    /// count.add_receiver(FnReceiver::new(|| println!("count changed")));
+    /// count.send(1);
+    /// ```
+    pub fn new(f: impl Fn() + 'static) -> Self {
+        api::FnReceiver::from_observer(Signal::derived(
+            (),
+            move |_| {
+                f();
+                Some(())
+            },
+            |_, _| {},
+        ))
+    }
+}
+
+impl<T: Clone + 'static> Signal<Signal<T>> {
+    /// Creates a signal following whichever signal this one currently holds.
+    ///
+    /// The flattened signal takes the value of the inner signal, and updates
+    /// when either the inner signal changes or this signal switches to another
+    /// one. On a switch, it stops listening to the previous inner signal, so
+    /// late updates of that signal are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let local = Signal::new("local");
+    /// let remote = Signal::new("remote");
+    /// let source = Signal::new(local.clone());
+    /// let data = source.flatten();
+    ///
+    /// source.send(remote.clone());
+    /// assert_eq!(data.get(), "remote");
+    ///
+    /// local.send("stale");
+    /// assert_eq!(data.get(), "remote");
+    /// remote.send("fresh");
+    /// assert_eq!(data.get(), "fresh");
+    /// ```
+    pub fn flatten(&self) -> Signal<T> {
+        // The inner signal currently listened to, by id
+        let current: Rc<RefCell<Option<(usize, Subscription)>>> = Rc::new(RefCell::new(None));
+        let (outer, outer_, current_) = (self.clone(), self.clone(), current.clone());
+        let signal = Signal::driven(
+            move || outer.borrow().get(),
+            move |signal, _| {
+                let inner = outer_.get();
+                let mut current = current_.borrow_mut();
+                if current.as_ref().map(|(id, _)| *id) != Some(inner.id()) {
+                    *current = Some((inner.id(), inner.subscribe(signal.clone())));
+                }
+            },
+        );
+        self.add_receiver(signal.clone());
+        let inner = self.get();
+        *current.borrow_mut() = Some((inner.id(), inner.subscribe(signal.clone())));
+        signal
+    }
+}
+
+impl<T: 'static, E: 'static> Signal<Result<T, E>> {
+    /// Creates a derived signal applying `f` to successful values.
+    ///
+    /// Errors are passed on unchanged without calling `f`, so while this signal
+    /// holds an error the derived signal holds the same error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let input = Signal::new("4".parse::<i32>());
+    /// let squared = input.map_ok(|n| n * n);
+    /// assert_eq!(squared.get(), Ok(16));
+    ///
+    /// input.send("x".parse::<i32>());
+    /// assert!(squared.get().is_err());
+    /// ```
+    pub fn map_ok<U: 'static, F>(&self, f: F) -> Signal<Result<U, E>>
+    where
+        E: Clone,
+        F: Fn(&T) -> U + 'static,
+    {
+        self.map(move |result| match result {
+            Ok(value) => Ok(f(value)),
+            Err(error) => Err(error.clone()),
+        })
+    }
+
+    /// Creates a derived signal applying `f` to errors.
+    ///
+    /// Successful values are passed on unchanged without calling `f`.
+    pub fn map_err<G: 'static, F>(&self, f: F) -> Signal<Result<T, G>>
+    where
+        T: Clone,
+        F: Fn(&E) -> G + 'static,
+    {
+        self.map(move |result| match result {
+            Ok(value) => Ok(value.clone()),
+            Err(error) => Err(f(error)),
+        })
+    }
+
+    /// Creates a derived signal applying the fallible `f` to successful values.
+    ///
+    /// Like `map_ok`, an error in this signal is passed on without calling `f`.
+    /// An error returned by `f` is held the same way, so errors flow down a
+    /// chain of `and_then` signals until a value succeeds again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # unsafe { reactivity::api::assume_single_context() }
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let input = Signal::new(Ok::<_, String>(8));
+    /// let halved = input.and_then(|n| {
+    ///     if n % 2 == 0 {
+    ///         Ok(n / 2)
+    ///     } else {
+    ///         Err(format!("{n} is odd"))
+    ///     }
+    /// });
+    /// assert_eq!(halved.get(), Ok(4));
+    ///
+    /// input.send(Ok(3));
+    /// assert_eq!(halved.get(), Err("3 is odd".to_string()));
+    /// ```
+    pub fn and_then<U: 'static, F>(&self, f: F) -> Signal<Result<U, E>>
+    where
+        E: Clone,
+        F: Fn(&T) -> Result<U, E> + 'static,
+    {
+        self.map(move |result| match result {
+            Ok(value) => f(value),
+            Err(error) => Err(error.clone()),
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, H> Signal<HashMap<K, V, H>>
+where
+    K: Eq + Hash + 'static,
+    V: Clone + PartialEq + 'static,
+    H: BuildHasher + 'static,
+{
+    /// Creates a signal following the value at `key`.
+    ///
+    /// The new signal is registered as a receiver of the map and holds
+    /// `Some` value while `key` is present and `None` otherwise. It checks the
+    /// key on each update of the map, but only passes the update on when the
+    /// value at the key changed, including when the key is removed or added
+    /// back, so observers of one key are not run for changes to another.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use reactivity::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let scores = Signal::new(HashMap::from([("ada", 3)]));
+    /// let ada = scores.key_signal("ada");
+    ///
+    /// scores.update(|scores| {
+    ///     scores.insert("grace", 5);
+    /// });
+    /// assert_eq!(ada.update_count(), 0);
+    ///
+    /// scores.update(|scores| {
+    ///     scores.remove("ada");
+    /// });
+    /// assert_eq!(ada.get(), None);
+    /// ```
+    pub fn key_signal(&self, key: K) -> Signal<Option<V>> {
+        let source = self.clone();
+        let signal = Signal::derived(
+            self.with(|map| map.get(&key).cloned()),
+            move |last| {
+                let value = source.with(|map| map.get(&key).cloned());
+                (value != *last).then_some(value)
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+}
+
+impl_ops!();
+
+impl<T: 'static> api::Source for Signal<T> {
+    fn add_dependent(&self, receiver: Box<dyn Receptive>) {
+        self.add_receiver(receiver);
+    }
+}
+
+impl<T: 'static> SealedSignalTrait for Signal<T> {
+    type Inner = T;
+    type Rc<U: ?Sized> = Rc<U>;
+    type Ptr<U> = RefCell<U>;
+    type Effect = dyn Fn(&Signal<T>, &T);
+    type Processor = dyn Fn(&T) -> Option<T>;
+    type Receiver = dyn Receptive;
+    type Cleanup = dyn FnOnce();
+    type Hook = dyn Fn(&T) -> bool;
+
+    fn from_state(state: Rc<State<Self>>) -> Self {
+        Self { state }
+    }
+
+    fn state(&self) -> &Rc<State<Self>> {
+        &self.state
+    }
+
+    fn track(&self) {
+        api::track(self);
+    }
+
+    fn inner_mut(&self) -> RefMut<'_, T> {
+        let Ok(inner) = self.inner().try_borrow_mut() else {
+            panic!(
+                "cannot send to {} while its value is borrowed, see `Signal::try_send`",
+                self.label()
+            );
+        };
+        inner
+    }
+}
+
+/// A macro to create reactive signals.
+///
+/// This macro supports creating both single-threaded signals (`reactivity::Signal`)
+/// and thread-safe signals (`reactivity::sync::Signal`) depending on the context.
+///
+/// # Syntax
+///
+/// ```text
+/// // Create a basic signal with a value
+/// signal!(value)
+///
+/// // Create a signal that reacts to other signals
+/// signal!([dep1, dep2, ...] expression)
+///
+/// // Name dependencies that are not plain variables
+/// signal!([name = some.expression, ...] expression)
+///
+/// // Create a signal with custom effect function
+/// signal!(<before, after> [dep1, dep2, ...] expression; effect_code)
+///
+/// // Create a signal without registering it as a receiver of its dependencies
+/// signal!(detached [dep1, dep2, ...] expression)
+/// ```
+///
+/// # Examples
+///
+/// ## Single-threaded usage
+///
+/// ```rust
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{signal, Signal};
+/// use reactivity::api::SignalTrait;
+///
+/// let x = signal!(1);
+/// let y = signal!([x] x * 2);
+///
+/// x.send(5);
+/// assert_eq!(y.get(), 10);
+/// ```
+///
+#[cfg_attr(
+    feature = "std",
+    doc = r#"
+## Thread-safe usage
+
+```rust
+use std::thread;
+use reactivity::signal;
+use reactivity::sync::Signal;
+use reactivity::api::SignalTrait;
+
+let x = signal!(1);
+let y = signal!([x] x * 2);
+let x_clone = x.clone();
+
+thread::spawn(move || {
+    x_clone.send(5);
+}).join().unwrap();
+
+assert_eq!(y.get(), 10);
+```
+"#
+)]
+///
+/// # Choosing Between Signal Types
+///
+/// - Use `reactivity::Signal` (imported with `use reactivity::Signal`) for single-threaded contexts
+/// - Use `reactivity::sync::Signal` (imported with `use reactivity::sync::Signal`) for multi-threaded contexts
+///
+/// The `signal!` macro will use the correct Signal implementation based on your imports.
+#[macro_export]
+macro_rules! signal {
+    // Each dependency is cloned once for the processor and, if there is an
+    // effect, once for the effect. A reference to the dependency itself
+    // registers the receiver.
+    ($(< $_before:ident $(, $_after:ident)? >)? [$($params:ident $(= $dep:expr)?),*] $($rest:tt)*) => {
+        {
+            use $crate::api::SignalTrait;
+            $(
+                let $params = &$crate::__signal_aux!($params $(= $dep)?);
+            )*
+            let signal = $crate::signal!(@driven $(<$_before $(, $_after)?>)? [$($params),*] $($rest)*);
+            $(
+                $params.add_receiver(signal.clone());
+            )*
+            signal
+        }
+    };
+    (detached $(< $_before:ident $(, $_after:ident)? >)? [$($params:ident $(= $dep:expr)?),*] $($rest:tt)*) => {
+        {
+            use $crate::api::SignalTrait;
+            $(
+                let $params = &$crate::__signal_aux!($params $(= $dep)?);
+            )*
+            $crate::signal!(@driven $(<$_before $(, $_after)?>)? [$($params),*] $($rest)*)
+        }
+    };
+    (@driven $(< $_before:ident $(, $_after:ident)? >)? [$($params:ident),*] $proc:expr) => {
+        {
+            $(
+                let $params = $params.clone();
+            )*
+            Signal::driven(move || {
+                $(
+                    #[allow(unused_variables)]
+                    let $params = $params.get();
+                )*
+                $proc
+            }, |_, _| {})
+        }
+    };
+    (@driven [$($params:ident),*] $proc:expr; $eff:expr) => {
+        {
+            $(
+                paste::paste!{ let [<$params _>] = $params.clone(); }
+            )*
+            $(
+                let $params = $params.clone();
+            )*
+            Signal::driven(move || {
+                $(
+                    #[allow(unused_variables)]
+                    let $params = $params.get();
+                )*
+                $proc
+            }, move |_, _| {
+                $(
+                    paste::paste!{
+                        #[allow(unused_variables)]
+                        let $params = &[<$params _>];
+                    }
+                )*
+                $eff
+            })
+        }
+    };
+    // The values are passed to the effect by `driven_prev`, so `before` is
+    // the value from before the update even when the effect is deferred.
+    (@driven < $_before:ident $(, $_after:ident)? > [$($params:ident),*] $proc:expr; $eff:expr) => {
+        {
+            $(
+                paste::paste!{ let [<$params _>] = $params.clone(); }
+            )*
+            $(
+                let $params = $params.clone();
+            )*
+            Signal::driven_prev(move || {
+                $(
+                    #[allow(unused_variables)]
+                    let $params = $params.get();
+                )*
+                $proc
+            }, move |_, _before, _after| {
+                let $_before = _before.clone();
+                $(
+                    let $_after = _after.clone();
+                )?
+                $(
+                    paste::paste!{
+                        #[allow(unused_variables)]
+                        let $params = &[<$params _>];
+                    }
+                )*
+                $eff
+            })
+        }
+    };
+
+    ($value:expr) => {
+        {
+            use $crate::api::SignalTrait;
+            Signal::new($value)
+        }
+    };
+}
+
+/// Creates an effect that runs whenever its dependencies change.
+///
+/// Dependencies are listed like in `signal!` and bound to their current values
+/// in the body. The body runs each time one of them changes, but not when the
+/// effect is created. Returns an `EffectHandle` that detaches the effect when
+/// dropped.
+///
+/// # Example
+///
+/// ```rust
+/// # unsafe { reactivity::api::assume_single_context() }
+/// use reactivity::{effect, signal, Signal};
+/// use reactivity::api::SignalTrait;
+///
+/// let first = signal!("Ada");
+/// let last = signal!("Lovelace");
+/// let _greeting = effect!([first, last] println!("Hello, {first} {last}"));
+///
+/// // Prints "Hello, Ada Byron"
+/// last.send("Byron");
+/// ```
+#[macro_export]
+macro_rules! effect {
+    ([$($params:ident $(= $dep:expr)?),*] $body:expr) => {
+        {
+            use $crate::api::SignalTrait;
+            $(
+                let $params = &$crate::__signal_aux!($params $(= $dep)?);
+            )*
+            let handle = {
+                $(
+                    let $params = $params.clone();
+                )*
+                Signal::effect(move || {
+                    $(
+                        #[allow(unused_variables)]
+                        let $params = $params.get();
+                    )*
+                    $body;
+                })
+            };
+            $(
+                $params.add_receiver(handle.observer().clone());
+            )*
+            handle
+        }
+    };
+}
+
+/// Resolves a `signal!` dependency to the signal it names.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __signal_aux {
+    ($name:ident) => {
+        $name
+    };
+    ($name:ident = $dep:expr) => {
+        $dep
+    };
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::thread;
+
+    use crate::{api::SignalTrait, sync::Signal};
+
+    /// Held by the tests that depend on `api::max_depth`, which is shared by
+    /// all threads.
+    static MAX_DEPTH: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn test() {
+        // Diamond dependency
+        let x = signal!(1);
+        let doubled_x = signal!([x] x * 2);
+        let tripled_x = signal!([x] x * 3);
+        let _sum = signal!(
+            <before, now> 
+            [doubled_x, tripled_x] 
+            doubled_x + tripled_x; 
+            println!("output {before} -> {now}"));
+        thread::spawn(move || {
+            for _ in 0..5 {
+                x.send_with(|x| x + 1);
+                thread::sleep(std::time::Duration::from_millis(100));
+            }
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn concurrent_sends_through_a_diamond_settle() {
+        let x = signal!(0);
+        let a = signal!([x] x + 1);
+        let c = signal!([x] x * 2);
+        let b = signal!([a, c] a + c);
+        let senders: Vec<_> = (0..8)
+            .map(|_| {
+                let x = x.clone();
+                thread::spawn(move || {
+                    for n in 0..500 {
+                        x.send(n);
+                    }
+                })
+            })
+            .collect();
+        for sender in senders {
+            sender.join().unwrap();
+        }
+
+        x.send(5);
+        assert_eq!(b.get(), 16);
+        assert_eq!(b.pending_updates(), 0);
+    }
+
+    #[test]
+    fn effects_may_wait_for_threads_that_send() {
+        let (x, y) = (signal!(0), signal!(0));
+        let doubled = signal!([y] y * 2);
+        let (x_, y_) = (x.clone(), y.clone());
+        let forward = Signal::driven(
+            move || x_.get(),
+            move |_, value| {
+                let (y, value) = (y_.clone(), *value);
+                thread::spawn(move || drop(y.send(value + 1)))
+                    .join()
+                    .unwrap();
+            },
+        );
+        x.add_receiver(forward.clone());
+
+        x.send(1);
+        assert_eq!(doubled.get(), 4);
+    }
+
+    #[test]
+    fn try_send_does_not_wait_for_updates_on_other_threads() {
+        use std::sync::mpsc;
+
+        let (started, wait) = (mpsc::channel(), mpsc::channel::<()>());
+        let (busy, level) = (signal!(0), signal!(0.0));
+        let halved = signal!([level] level / 2.0);
+        let busy_ = busy.clone();
+        let (started_, wait_) = (started.0, std::sync::Mutex::new(wait.1));
+        let blocked = Signal::driven(
+            move || busy_.get(),
+            move |_, _| {
+                started_.send(()).unwrap();
+                wait_.lock().unwrap().recv().unwrap();
+            },
+        );
+        busy.add_receiver(blocked.clone());
+        let sender = thread::spawn(move || drop(busy.send(1)));
+
+        started.1.recv().unwrap();
+        assert_eq!(level.try_send(0.5), Ok(()));
+        assert_eq!(halved.get(), 0.25);
+        wait.0.send(()).unwrap();
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn on_value_sees_every_value_sent_from_other_threads() {
+        use std::sync::{Arc, Mutex};
+
+        let x = signal!(0);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_ = seen.clone();
+        let _values = x.on_value(move |value| seen_.lock().unwrap().push(*value));
+        let senders: Vec<_> = [1, 1001]
+            .into_iter()
+            .map(|start| {
+                let x = x.clone();
+                thread::spawn(move || (start..start + 500).for_each(|n| drop(x.send(n))))
+            })
+            .collect();
+        for sender in senders {
+            sender.join().unwrap();
+        }
+
+        let seen = seen.lock().unwrap();
+        for start in [1, 1001] {
+            let from_thread: Vec<_> = seen
+                .iter()
+                .copied()
+                .filter(|n| *n >= start && *n < start + 500)
+                .collect();
+            assert_eq!(from_thread, (start..start + 500).collect::<Vec<_>>());
+        }
+        assert_eq!(seen.len(), 1000);
+    }
+
+    #[test]
+    fn remove_receiver_inside_effect() {
+        let x = signal!(1);
+        let y = signal!([x] x + 1);
+        let (x_, y_) = (x.clone(), y.clone());
+        let _z = signal!([x] x; {
+            x_.remove_receiver(&y_);
+        });
+
+        x.send(2);
+        assert_eq!(y.get(), 3);
+        x.send(3);
+        assert_eq!(y.get(), 3);
+        assert!(!x.remove_receiver(&y));
+    }
+
+    #[test]
+    fn subscription_detaches_on_drop() {
+        let x = signal!(1);
+        let x_ = x.clone();
+        let y = Signal::driven(move || x_.get() + 1, |_, _| {});
+
+        let subscription = x.subscribe(y.clone());
+        let handle = thread::spawn(move || drop(subscription));
+        handle.join().unwrap();
+        x.send(2);
+        assert_eq!(y.get(), 2);
+
+        x.subscribe(y.clone()).forget();
+        x.send(3);
+        assert_eq!(y.get(), 4);
+    }
+
+    #[test]
+    fn dropped_receivers_are_freed() {
+        use std::sync::{Arc, Mutex};
+
+        let x = signal!(1);
+        let y = signal!([x] x + 1);
+        let weak_y = y.downgrade();
+        drop(y);
+        assert!(weak_y.upgrade().is_none());
+        x.send(2);
+
+        // Dropping a receiver while an update is in flight
+        let held = Arc::new(Mutex::new(Some(signal!([x] x * 2))));
+        let weak_held = held.lock().unwrap().as_ref().unwrap().downgrade();
+        let held_ = held.clone();
+        let _dropper = signal!([x] x; {
+            held_.lock().unwrap().take();
+        });
+        x.send(3);
+        assert!(weak_held.upgrade().is_none());
+        x.send(4);
+    }
+
+    #[test]
+    fn dead_receivers_are_pruned() {
+        use crate::api::SealedSignalTrait;
+
+        // Receivers of the other signal type cannot detach themselves
+        let x = crate::Signal::new(1);
+        for _ in 0..3 {
+            x.add_receiver(Signal::new(0));
+        }
+        assert_eq!(x.receiver_count(), 0);
+        assert_eq!(x.receivers().borrow().len(), 3);
+
+        x.send(2);
+        assert!(x.receivers().borrow().is_empty());
+    }
+
+    #[test]
+    fn dropped_signals_detach_from_sources() {
+        let (a, b) = (signal!(1), signal!(2));
+        let sum = signal!([a, b] a + b);
+        let doubled = signal!([sum] sum * 2);
+        assert_eq!(a.receiver_count(), 1);
+
+        drop(sum);
+        // `doubled` still holds `sum` through its processor
+        assert_eq!(a.receiver_count(), 1);
+        a.send(10);
+        assert_eq!(doubled.get(), 24);
+
+        drop(doubled);
+        assert_eq!(a.receiver_count(), 0);
+        assert_eq!(b.receiver_count(), 0);
+    }
+
+    #[test]
+    fn unchanged_values_stop_propagation() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let x = signal!(1);
+        let x_ = x.clone();
+        let parity = Signal::driven_eq(move || x_.get() % 2, |_, _| {});
+        x.add_receiver(parity.clone());
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_ = runs.clone();
+        let _leaf = signal!([parity, x] (parity, x); {
+            runs_.fetch_add(1, Ordering::SeqCst);
+        });
+        let runs_ = runs.clone();
+        let only_parity = signal!([parity] parity; {
+            runs_.fetch_add(1, Ordering::SeqCst);
+        });
+
+        x.send(3);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        x.send(4);
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(only_parity.get(), 0);
+        assert_eq!(only_parity.pending_updates(), 0);
+    }
+
+    #[test]
+    fn set_if_changed_skips_equal_values() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let x = signal!(1);
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_ = runs.clone();
+        let _y = signal!([x] x; {
+            runs_.fetch_add(1, Ordering::SeqCst);
+        });
+
+        assert!(!x.set_if_changed(1));
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        assert!(x.set_if_changed(2));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn values_need_not_be_clone() {
+        struct Reading(u32);
+
+        let x = Signal::new(Reading(1));
+        let doubled = x.map(|reading| Reading(reading.0 * 2));
+        let y = x.clone();
+
+        y.send(Reading(2));
+        assert_eq!(doubled.with(|reading| reading.0), 4);
+        assert_eq!(x.borrow().0, 2);
+    }
+
+    /// Runs a future to completion on the current thread.
+    #[cfg(feature = "futures")]
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        use std::{
+            pin::pin,
+            sync::Arc,
+            task::{Context, Poll, Wake},
+        };
+
+        struct Unpark(thread::Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Arc::new(Unpark(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn stream_buffers_latest_values() {
+        let x = signal!(0);
+        let mut stream = x.stream_with_capacity(2);
+        for i in 1..=3 {
+            x.send(i);
+        }
+        assert_eq!(stream.len(), 2);
+        assert_eq!(block_on(stream.next()), Some(2));
+        assert_eq!(block_on(stream.next()), Some(3));
+
+        let x_ = x.clone();
+        let producer = thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(50));
+            x_.send(4);
+        });
+        assert_eq!(block_on(stream.next()), Some(4));
+        producer.join().unwrap();
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn stream_buffers_each_value_sent_from_other_threads() {
+        let x = signal!(0);
+        let mut stream = x.stream_with_capacity(1000);
+        let senders: Vec<_> = [1, 1001]
+            .into_iter()
+            .map(|start| {
+                let x = x.clone();
+                thread::spawn(move || (start..start + 500).for_each(|n| drop(x.send(n))))
+            })
+            .collect();
+        for sender in senders {
+            sender.join().unwrap();
+        }
+
+        let mut values: Vec<_> = (0..stream.len())
+            .map(|_| block_on(stream.next()).unwrap())
+            .collect();
+        values.sort_unstable();
+        assert_eq!(values, (1..501).chain(1001..1501).collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn changed_resolves_once() {
+        let x = signal!(0);
+        let changed = x.changed();
+        assert_eq!(x.receiver_count(), 1);
+        // Sent before the first poll
+        x.send(1);
+        x.send(2);
+        assert_eq!(block_on(changed), 1);
+        assert_eq!(x.receiver_count(), 0);
+
+        let changed = x.changed();
+        let x_ = x.clone();
+        let producer = thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(50));
+            x_.send(3);
+        });
+        assert_eq!(block_on(changed), 3);
+        producer.join().unwrap();
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn from_future_sends_when_ready() {
+        use crate::future::Async;
+        use std::sync::{Arc, Mutex};
+
+        let workers = Arc::new(Mutex::new(Vec::new()));
+        let workers_ = workers.clone();
+        let spawn = move |task| {
+            let worker = thread::spawn(move || block_on(task));
+            workers_.lock().unwrap().push(worker);
+        };
+        let join = || {
+            let workers = std::mem::take(&mut *workers.lock().unwrap());
+            workers
+                .into_iter()
+                .for_each(|worker| worker.join().unwrap());
+        };
+
+        let trigger = signal!(0);
+        let data = Signal::from_future(trigger.changed(), spawn.clone());
+        let loaded = signal!([data] data.is_ready());
+        assert!(!loaded.get());
+        trigger.send(5);
+        join();
+        assert_eq!(data.get(), Async::Ready(5));
+        assert!(loaded.get());
+
+        // The task outlives the signal
+        drop(Signal::from_future(trigger.changed(), spawn));
+        trigger.send(6);
+        join();
+    }
+
+    #[test]
+    #[cfg(feature = "futures")]
+    fn map_async_drops_superseded_computations() {
+        use crate::future::{Async, Task};
+        use std::{
+            future,
+            sync::{
+                atomic::{AtomicBool, Ordering},
+                Arc, Mutex,
+            },
+            task::{Context, Waker},
+        };
+
+        struct Guard(Arc<AtomicBool>);
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let tasks = Arc::new(Mutex::new(Vec::<Task>::new()));
+        let tasks_ = tasks.clone();
+        let dropped = Arc::new(AtomicBool::new(false));
+        let dropped_ = dropped.clone();
+        let id = signal!(1);
+        let user = id.map_async(
+            move |id| {
+                let guard = (id == 1).then(|| Guard(dropped_.clone()));
+                async move {
+                    if guard.is_some() {
+                        future::pending::<()>().await;
+                    }
+                    id * 10
+                }
+            },
+            move |task| tasks_.lock().unwrap().push(task),
+        );
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut first = tasks.lock().unwrap().remove(0);
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+
+        id.send(2);
+        assert!(first.as_mut().poll(&mut cx).is_ready());
+        assert!(dropped.load(Ordering::SeqCst));
+        assert_eq!(user.get(), Async::Pending);
+
+        let mut second = tasks.lock().unwrap().remove(0);
+        assert!(second.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(user.get(), Async::Ready(20));
+    }
+
+    /// Collects scheduled callbacks so tests can run them by hand.
+    #[cfg(feature = "timer")]
+    #[allow(clippy::type_complexity)]
+    fn manual_scheduler() -> (
+        impl Fn(std::time::Duration, crate::timer::Callback) + Send + Sync + 'static,
+        impl Fn() -> usize,
+    ) {
+        use std::sync::{Arc, Mutex};
+
+        let pending = Arc::new(Mutex::new(Vec::<crate::timer::Callback>::new()));
+        let pending_ = pending.clone();
+        let schedule = move |_, callback| pending_.lock().unwrap().push(callback);
+        let run = move || {
+            let callbacks = std::mem::take(&mut *pending.lock().unwrap());
+            let count = callbacks.len();
+            callbacks.into_iter().for_each(|callback| callback());
+            count
+        };
+        (schedule, run)
+    }
+
+    #[cfg(feature = "timer")]
+    #[test]
+    fn debounce_takes_latest_after_quiet() {
+        use std::time::Duration;
+
+        let (schedule, run) = manual_scheduler();
+        let x = signal!(0);
+        let settled = x.debounce(Duration::from_millis(10), schedule);
+        x.send(1);
+        x.send(2);
+        assert_eq!(settled.get(), 0);
+        assert_eq!(run(), 2);
+        assert_eq!(settled.get(), 2);
+    }
+
+    #[cfg(feature = "timer")]
+    #[test]
+    fn leading_debounce_ignores_the_rest_of_a_burst() {
+        use crate::timer::Edge;
+        use std::time::Duration;
+
+        let (schedule, run) = manual_scheduler();
+        let x = signal!(0);
+        let pressed = x.debounce_edge(Duration::from_millis(10), Edge::Leading, schedule);
+        x.send(1);
+        x.send(2);
+        assert_eq!(pressed.get(), 1);
+        assert_eq!(run(), 2);
+        assert_eq!(pressed.get(), 1);
+
+        // The burst is over, so the next update is taken right away
+        x.send(3);
+        assert_eq!(pressed.get(), 3);
+        assert_eq!(pressed.update_count(), 2);
+    }
+
+    #[cfg(feature = "timer")]
+    #[test]
+    fn throttle_emits_trailing_value() {
+        use std::time::Duration;
+
+        let (schedule, run) = manual_scheduler();
+        let x = signal!(0);
+        let sampled = x.throttle(Duration::from_millis(10), schedule);
+        x.send(1);
+        x.send(2);
+        x.send(3);
+        assert_eq!(sampled.get(), 1);
+        // The trailing value opens another window
+        assert_eq!(run(), 1);
+        assert_eq!(sampled.get(), 3);
+        assert_eq!(run(), 1);
+        assert_eq!(run(), 0);
+
+        x.send(4);
+        assert_eq!(sampled.get(), 4);
+    }
+
+    #[test]
+    fn operators_create_derived_signals() {
+        let (a, b) = (signal!(6), signal!(3));
+        let sum = &a + &b;
+        let ratio = &(&a * &b) / &b;
+        a.send(10);
+        assert_eq!(sum.value(), 13);
+        assert_eq!(ratio.value(), 10);
+        b.send(5);
+        assert_eq!((sum.value(), ratio.value()), (15, 10));
+    }
+
+    #[test]
+    fn macro_clones_dependencies_minimally() {
+        use crate::Signal;
+        use std::rc::Rc;
+
+        let (a, b, c, d, e) = (signal!(1), signal!(2), signal!(3), signal!(4), signal!(5));
+        let sum = signal!([a, b, c, d, e] a + b + c + d + e);
+        assert_eq!(Rc::strong_count(&a.state), 2);
+        assert_eq!(Rc::strong_count(&e.state), 2);
+
+        let logged = signal!(<before, after> [a, sum] a * sum; {
+            assert_ne!(before, after);
+            assert_eq!(after, a.get() * sum.get());
+        });
+        assert_eq!(Rc::strong_count(&a.state), 4);
+        assert_eq!(Rc::strong_count(&sum.state), 3);
+
+        a.send(10);
+        assert_eq!(sum.get(), 24);
+        assert_eq!(logged.get(), 240);
+        drop((sum, logged));
+        assert_eq!(Rc::strong_count(&a.state), 1);
+    }
+
+    #[test]
+    fn macro_accepts_expression_dependencies() {
+        struct Counter {
+            count: Signal<i32>,
+            steps: Vec<Signal<i32>>,
+        }
+
+        let counter = Counter {
+            count: signal!(1),
+            steps: vec![signal!(10)],
+        };
+        let total = signal!([count = counter.count, step = counter.steps[0]] count + step);
+        let doubled = signal!([total, count = counter.count] total * 2; {
+            assert!(count.get() > 1);
+        });
+
+        counter.count.send(2);
+        counter.steps[0].send(20);
+        assert_eq!(total.get(), 22);
+        assert_eq!(doubled.get(), 44);
+    }
+
+    #[test]
+    fn detached_signals_are_wired_by_hand() {
+        let x = signal!(1);
+        let y = signal!(detached [x] x + 1);
+        let z = signal!(detached <before, after> [x] x * 2; assert!(after > before));
+        assert_eq!(x.receiver_count(), 0);
+        x.send(2);
+        assert_eq!((y.get(), z.get()), (2, 2));
+
+        x.add_receiver(y.clone());
+        x.add_receiver(z.clone());
+        x.send(3);
+        assert_eq!((y.get(), z.get()), (4, 6));
+    }
+
+    #[test]
+    fn debug_graph_lists_edges() {
+        use crate::api::Receptive;
+
+        let x = signal!(1).named("x");
+        let y = signal!([x] x + 1).named("y");
+        let z = signal!([x, y] x + y).named("z");
+
+        let dot = x.debug_graph();
+        let node = |signal: &Signal<i32>| format!("n{:x}", signal.id());
+        for (from, to) in [(&x, &y), (&x, &z), (&y, &z)] {
+            assert!(dot.contains(&format!("{} -> {};", node(from), node(to))));
+        }
+        assert_eq!(dot.matches("label=").count(), 3);
+        assert!(y.debug_graph().contains("[label=\"z\"]"));
+    }
+
+    #[test]
+    fn batch_propagates_once() {
+        use crate::batch;
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let (a, b, c) = (signal!(1), signal!(2), signal!(3));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_ = runs.clone();
+        let sum = signal!([a, b, c] a + b + c; {
+            runs_.fetch_add(1, Ordering::SeqCst);
+        });
+
+        batch(|| {
+            a.send(10);
+            batch(|| b.send(20));
+            assert_eq!(sum.get(), 6);
+            c.send(30);
+        });
+        assert_eq!(sum.get(), 60);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn diamond_is_glitch_free() {
+        use std::sync::{Arc, Mutex};
+
+        let x = signal!(1);
+        let doubled_x = signal!([x] x * 2);
         let tripled_x = signal!([x] x * 3);
-        let _ = signal!(
-            <before, now> 
-            [doubled_x, tripled_x] 
-            doubled_x + tripled_x; 
-            println!("output {before} -> {now}"));
-        thread::spawn(move || loop {
-            x.send(x.get() + 1);
-            thread::sleep(std::time::Duration::from_millis(100));
-        })
-        .join()
-        .unwrap();
+        // A longer path into the same sink
+        let quadrupled_x = signal!([doubled_x] doubled_x * 2);
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_ = seen.clone();
+        let sum = signal!(<_before, now> [doubled_x, tripled_x, quadrupled_x, x]
+            doubled_x + tripled_x + quadrupled_x - x;
+            seen_.lock().unwrap().push(now));
+
+        for value in 2..=4 {
+            x.send(value);
+        }
+        assert_eq!(*seen.lock().unwrap(), vec![16, 24, 32]);
+        assert_eq!(sum.update_count(), 3);
+        assert!(!quadrupled_x.is_dirty());
+    }
+
+    #[test]
+    fn propagation_orders_agree() {
+        use crate::{set_propagation_order, Order};
+        use std::sync::{Arc, Mutex};
+
+        let mut finals = Vec::new();
+        for order in [Order::DepthFirst, Order::BreadthFirst] {
+            set_propagation_order(order);
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let log_ = |name| {
+                let log = log.clone();
+                move || log.lock().unwrap().push(name)
+            };
+            let (log_b, log_b2, log_c, log_d) = (log_("b"), log_("b2"), log_("c"), log_("d"));
+            let a = signal!(1);
+            let b = signal!([a] a + 1; log_b());
+            let b2 = signal!([b] b * 10; log_b2());
+            let c = signal!([a] a * 2; log_c());
+            let d = signal!([b2, c] b2 + c; log_d());
+
+            a.send(5);
+            finals.push(d.get());
+            assert_eq!(d.update_count(), 1);
+            let expected = match order {
+                Order::DepthFirst => ["b", "b2", "c", "d"],
+                Order::BreadthFirst => ["b", "c", "b2", "d"],
+            };
+            assert_eq!(*log.lock().unwrap(), expected);
+        }
+        set_propagation_order(Order::DepthFirst);
+        assert_eq!(finals, [70, 70]);
+    }
+
+    #[test]
+    fn subscribe_once_fires_once() {
+        use std::sync::{Arc, Mutex};
+
+        let x = signal!(0);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_ = seen.clone();
+        let _once = x.subscribe_once(move |x| seen_.lock().unwrap().push(*x));
+        let doubled = signal!([x] x * 2);
+
+        x.send(1);
+        x.send(2);
+        assert_eq!(*seen.lock().unwrap(), vec![1]);
+        assert_eq!(doubled.get(), 4);
+        assert_eq!(x.receiver_count(), 1);
+    }
+
+    #[test]
+    fn send_with_is_atomic() {
+        let count = signal!(0);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let count = count.clone();
+                thread::spawn(move || {
+                    for _ in 0..500 {
+                        count.send_with(|count| count + 1);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(count.get(), 2000);
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn receiver_ids_follow_the_graph() {
+        use crate::api::Receptive;
+
+        let x = signal!(1).named("x");
+        let doubled = signal!([x] x * 2).named("doubled");
+        let tripled = signal!([x] x * 3).named("tripled");
+        assert_eq!(x.receiver_ids(), vec![doubled.id(), tripled.id()]);
+        assert_eq!(x.receiver_labels(), vec!["doubled", "tripled"]);
+
+        drop(doubled);
+        assert_eq!(x.receiver_ids(), vec![tripled.id()]);
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn collect_records_updates_from_other_threads() {
+        let count = signal!(0);
+        let history = count.collect();
+
+        for i in 1..=3 {
+            let count = count.clone();
+            std::thread::spawn(move || {
+                count.send(i);
+            })
+            .join()
+            .unwrap();
+        }
+        assert_eq!(*history.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn signals_stay_usable_after_a_panic_holding_a_lock() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let count = signal!(1);
+        let doubled = signal!([count] count * 2);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _value = count.borrow_mut();
+            panic!("panicked while writing");
+        }));
+        assert!(result.is_err());
+
+        count.send(2);
+        assert_eq!(doubled.get(), 4);
+    }
+
+    #[test]
+    fn paused_signals_hold_back_their_receivers() {
+        use std::sync::{Arc, Mutex};
+
+        let x = signal!(1);
+        let doubled = signal!([x] x * 2);
+        let runs = Arc::new(Mutex::new(0));
+        let runs_ = runs.clone();
+        let _effect = effect!([doubled] * runs_.lock().unwrap() += 1);
+        let tripled = signal!([x] x * 3);
+
+        doubled.pause();
+        doubled.pause();
+        x.send(2);
+        x.send(3);
+        assert_eq!(doubled.get(), 6);
+        assert_eq!(tripled.get(), 9);
+        assert_eq!(*runs.lock().unwrap(), 0);
+
+        doubled.resume();
+        assert!(doubled.is_paused());
+        assert_eq!(*runs.lock().unwrap(), 0);
+        doubled.resume();
+        assert!(!doubled.is_paused());
+        assert_eq!(*runs.lock().unwrap(), 1);
+
+        doubled.pause();
+        doubled.resume();
+        assert_eq!(*runs.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn on_drop_runs_when_the_last_clone_drops() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let drops_ = drops.clone();
+        let x = signal!(1);
+        x.on_drop(move || {
+            drops_.fetch_add(1, Ordering::SeqCst);
+        });
+        let doubled = signal!([x] x * 2);
+
+        // The processor of `doubled` holds a clone as well
+        drop(x);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        thread::spawn(move || drop(doubled)).join().unwrap();
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn counts_return_to_baseline_after_derived_signals_drop() {
+        let x = signal!(1);
+        let (strong, weak) = (x.strong_count(), x.weak_count());
+
+        let doubled = signal!([x] x * 2);
+        let sum = signal!([x, doubled] x + doubled);
+        assert_eq!(x.strong_count(), strong + 2);
+        assert!(x.weak_count() > weak);
+
+        drop(sum);
+        drop(doubled);
+        assert_eq!(x.strong_count(), strong);
+        assert_eq!(x.weak_count(), weak);
+    }
+
+    #[test]
+    fn merge_takes_the_last_value_sent_in_a_batch() {
+        use crate::batch;
+
+        let a = signal!(0);
+        let b = signal!(0);
+        let merged = Signal::merge(&[a.clone(), b.clone()]);
+        let doubled = signal!([merged] merged * 2);
+
+        batch(|| {
+            b.send(2);
+            a.send(1);
+        });
+        assert_eq!(merged.get(), 1);
+        assert_eq!(doubled.get(), 2);
+
+        drop(a);
+        b.send(3);
+        assert_eq!(doubled.get(), 6);
+    }
+
+    #[test]
+    fn a_panicking_effect_leaves_no_signal_pending() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let x = signal!(1);
+        let doubled = signal!([x] x * 2);
+        let checked = signal!(<_old, new> [doubled] doubled; assert!(new < 10, "too large"));
+        let sum = signal!([x, checked] x + checked);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            x.send(5);
+        }));
+        assert!(result.is_err());
+        assert_eq!(sum.pending_updates(), 0);
+        assert_eq!(checked.pending_updates(), 0);
+
+        x.send(2);
+        assert_eq!(sum.get(), 6);
+    }
+
+    #[test]
+    fn updates_reach_siblings_after_an_effect_panicked() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let x = signal!(1);
+        let checked = signal!(<_old, new> [x] x; assert!(new != 5, "five"));
+        let below = signal!([checked] checked + 1);
+        let sibling = signal!([x] x * 10);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            x.send(5);
+        }));
+        assert!(result.is_err());
+        assert_eq!(below.pending_updates(), 0);
+
+        x.send(2);
+        assert_eq!(below.get(), 3);
+        assert_eq!(sibling.get(), 20);
+    }
+
+    #[test]
+    fn diff_only_moves_items_out_of_order() {
+        use crate::diff::{diff, Diff};
+
+        assert_eq!(
+            diff(&[1, 2, 3, 4], &[1, 3, 4], |n| *n),
+            vec![Diff::Removed { index: 1, value: 2 }]
+        );
+        assert_eq!(
+            diff(&[1, 2, 3], &[3, 2, 1], |n| *n),
+            vec![
+                Diff::Moved {
+                    from: 2,
+                    to: 0,
+                    value: 3
+                },
+                Diff::Moved {
+                    from: 1,
+                    to: 1,
+                    value: 2
+                },
+            ]
+        );
+
+        let list = signal!(vec![1, 2]);
+        let changes = list.diff_map(|n| *n);
+        assert_eq!(changes.get().len(), 2);
+        list.send(vec![1, 2]);
+        assert_eq!(changes.update_count(), 0);
+    }
+
+    #[test]
+    fn lazy_signals_compute_when_a_dependency_updates_first() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let effects = Arc::new(AtomicUsize::new(0));
+        let count = signal!(1);
+        let (source, runs_, effects_) = (count.clone(), runs.clone(), effects.clone());
+        let doubled = Signal::driven_lazy(
+            move || {
+                runs_.fetch_add(1, Ordering::Relaxed);
+                source.get() * 2
+            },
+            move |_, _| {
+                effects_.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+        count.add_receiver(doubled.clone());
+        assert_eq!(runs.load(Ordering::Relaxed), 0);
+
+        count.send(2);
+        assert_eq!(doubled.get(), 4);
+        assert_eq!(doubled.get(), 4);
+        assert_eq!(effects.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn subscribing_to_a_lazy_signal_computes_it() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let count = signal!(1);
+        let (source, runs_) = (count.clone(), runs.clone());
+        let doubled = Signal::driven_lazy(
+            move || {
+                runs_.fetch_add(1, Ordering::Relaxed);
+                source.get() * 2
+            },
+            |_, _| {},
+        );
+        count.add_receiver(doubled.clone());
+
+        let seen = Arc::new(AtomicUsize::new(0));
+        let seen_ = seen.clone();
+        let _watch = doubled.on_value(move |value| seen_.store(*value, Ordering::Relaxed));
+        assert_eq!(runs.load(Ordering::Relaxed), 1);
+
+        count.send(3);
+        assert_eq!(seen.load(Ordering::Relaxed), 6);
+        assert_eq!(runs.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn macro_effects_see_the_value_from_before_the_update() {
+        use std::sync::{Arc, Mutex};
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_ = seen.clone();
+        let x = signal!(1);
+        let _y = signal!(<before, now> [x] x; seen_.lock().unwrap().push((before, now)));
+
+        x.send(2);
+        x.send(3);
+        assert_eq!(*seen.lock().unwrap(), vec![(1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn watchers_get_each_transition_until_unsubscribed() {
+        use std::sync::{Arc, Mutex};
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_ = seen.clone();
+        let x = signal!(1);
+        let watch = x.watch(move |old, new| seen_.lock().unwrap().push((*old, *new)));
+
+        x.send(2);
+        x.send(5);
+        drop(watch);
+        x.send(7);
+        assert_eq!(*seen.lock().unwrap(), vec![(1, 2), (2, 5)]);
+        assert_eq!(x.receiver_count(), 0);
+    }
+
+    #[test]
+    fn effects_sending_to_their_source_settle_in_rounds() {
+        let x = signal!(10);
+        let x_ = x.clone();
+        let clamped = Signal::driven(
+            {
+                let x = x.clone();
+                move || x.get()
+            },
+            move |_, value| {
+                if *value > 5 {
+                    x_.send(5);
+                }
+            },
+        );
+        x.add_receiver(clamped.clone());
+
+        x.send(8);
+        assert_eq!(x.get(), 5);
+        assert_eq!(clamped.get(), 5);
+        assert_eq!(clamped.update_count(), 2);
+    }
+
+    #[test]
+    fn endless_feedback_stops_at_the_max_depth() {
+        use crate::api::DEFAULT_MAX_DEPTH;
+
+        let _max_depth = MAX_DEPTH.lock();
+        let x = signal!(0);
+        let x_ = x.clone();
+        let next = Signal::driven(
+            {
+                let x = x.clone();
+                move || x.get()
+            },
+            move |_, value| {
+                x_.send(value + 1);
+            },
+        );
+        x.add_receiver(next.clone());
+
+        x.send(1);
+        assert_eq!(x.get(), DEFAULT_MAX_DEPTH + 1);
+        assert_eq!(next.get(), DEFAULT_MAX_DEPTH);
+        assert!(!next.is_dirty());
+    }
+
+    #[test]
+    fn receivers_added_by_an_effect_join_the_next_update() {
+        use std::sync::{Arc, Mutex};
+
+        let x = signal!(0);
+        let late = Arc::new(Mutex::new(Vec::new()));
+        let (source, late_) = (x.clone(), late.clone());
+        let adder = Signal::driven(
+            {
+                let x = x.clone();
+                move || x.get()
+            },
+            move |_, _| {
+                let x = source.clone();
+                let receiver = signal!(detached [x] x * 10);
+                source.add_receiver(receiver.clone());
+                late_.lock().unwrap().push(receiver);
+            },
+        );
+        x.add_receiver(adder.clone());
+
+        x.send(1);
+        let first = late.lock().unwrap()[0].clone();
+        assert_eq!(first.update_count(), 0);
+        assert_eq!(first.get(), 10);
+
+        x.send(2);
+        assert_eq!(first.update_count(), 1);
+        assert_eq!(first.get(), 20);
+        assert_eq!(late.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn bound_signals_stop_once_they_agree() {
+        let number = signal!(1);
+        let text = signal!(String::new());
+        Signal::bind(&number, &text, i32::to_string, |text| {
+            text.parse().unwrap_or(0)
+        });
+        assert_eq!(text.get(), "1");
+
+        number.send(5);
+        assert_eq!(text.get(), "5");
+        text.send("12".to_string());
+        assert_eq!(number.get(), 12);
+        assert_eq!(number.update_count(), 2);
+        assert_eq!(text.update_count(), 3);
+
+        // A value that does not survive the round trip is replaced once
+        text.send("x".to_string());
+        assert_eq!(number.get(), 0);
+        assert_eq!(text.get(), "0");
+    }
+
+    #[test]
+    fn fn_receivers_are_owned_by_their_source() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        use crate::sync::FnReceiver;
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_ = runs.clone();
+        let x = signal!(0);
+        x.add_receiver(FnReceiver::new(move || {
+            runs_.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        x.send(1);
+        x.send(2);
+        assert_eq!(runs.load(Ordering::Relaxed), 2);
+        assert_eq!(x.receiver_count(), 1);
+
+        drop(x);
+        assert_eq!(Arc::strong_count(&runs), 1);
+    }
+
+    #[test]
+    fn deterministic_mode_follows_creation_order() {
+        use crate::{set_deterministic, sync::FnReceiver};
+        use std::sync::{Arc, Mutex};
+
+        set_deterministic(true);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let receiver = |name| {
+            let log = log.clone();
+            FnReceiver::new(move || log.lock().unwrap().push(name))
+        };
+        let (a, b, c, urgent) = (
+            receiver("a"),
+            receiver("b"),
+            receiver("c"),
+            receiver("urgent"),
+        );
+        let x = signal!(0);
+        x.add_receiver(c);
+        x.add_receiver(a);
+        x.add_receiver_with_priority(urgent, 1);
+        x.add_receiver(b);
+
+        x.send(1);
+        set_deterministic(false);
+        assert_eq!(*log.lock().unwrap(), ["urgent", "a", "b", "c"]);
+
+        log.lock().unwrap().clear();
+        x.send(2);
+        assert_eq!(*log.lock().unwrap(), ["urgent", "c", "a", "b"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot send to count while its value is borrowed")]
+    fn sending_while_borrowed_panics_with_the_signal() {
+        let count = crate::Signal::new(1).named("count");
+        count.with(|value| count.send(value + 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot send to count while its value is borrowed")]
+    fn updating_while_borrowed_panics_with_the_signal() {
+        let count = crate::Signal::new(1).named("count");
+        count.with(|_| count.update(|value| *value += 1));
+    }
+
+    #[test]
+    fn every_writer_checks_the_borrow() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let count = crate::Signal::new(1);
+        let writers: [&dyn Fn(); 3] = [
+            &|| drop(count.send_with(|value| value + 1)),
+            &|| {
+                count.replace(2);
+            },
+            &|| {
+                count.set_if_changed(2);
+            },
+        ];
+        for write in writers {
+            let panic = catch_unwind(AssertUnwindSafe(|| count.with(|_| write())));
+            let message = panic.unwrap_err().downcast::<String>().unwrap();
+            assert!(message.contains("while its value is borrowed"));
+        }
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn stateful_processors_may_read_their_own_signal() {
+        use crate::api::WeakSignal;
+        use std::sync::{Arc, OnceLock};
+
+        // Weak, so the processor does not keep its own signal alive
+        let this = Arc::new(OnceLock::<WeakSignal<crate::sync::Signal<i32>>>::new());
+        let (x, this_) = (signal!(1), this.clone());
+        let source = x.clone();
+        let total = crate::sync::Signal::driven_stateful(
+            0,
+            move |previous| {
+                assert_eq!(this_.get().unwrap().upgrade().unwrap().get(), *previous);
+                previous + source.get()
+            },
+            |_, _| {},
+        );
+        assert!(this.set(total.downgrade()).is_ok());
+        x.add_receiver(total.clone());
+
+        x.send(2);
+        x.send(3);
+        assert_eq!(total.get(), 5);
+    }
+
+    #[test]
+    fn computed_signals_depend_on_what_they_read() {
+        use crate::Signal;
+
+        let flag = Signal::new(false);
+        let (a, b, c) = (Signal::new(1), Signal::new(10), Signal::new(100));
+        let (flag_, a_, b_, c_) = (flag.clone(), a.clone(), b.clone(), c.clone());
+        let value = Signal::computed(move || {
+            let picked = if flag_.get() { b_.get() } else { a_.get() };
+            picked + c_.peek()
+        });
+        assert_eq!((a.receiver_count(), b.receiver_count()), (1, 0));
+
+        c.send(200);
+        assert_eq!(value.get(), 101);
+        assert_eq!(c.receiver_count(), 0);
+
+        flag.send(true);
+        assert_eq!(value.get(), 210);
+        b.send(20);
+        assert_eq!(value.get(), 220);
+        assert_eq!(b.receiver_count(), 1);
+        assert_eq!(value.update_count(), 2);
+    }
+
+    #[test]
+    fn computed_signals_depend_on_fallible_reads() {
+        use crate::Signal;
+
+        let count = Signal::new(1);
+        let count_ = count.clone();
+        let doubled = Signal::computed(move || count_.try_get().unwrap_or(0) * 2);
+        assert_eq!(count.receiver_count(), 1);
+
+        count.send(4);
+        assert_eq!(doubled.get(), 8);
+    }
+
+    #[test]
+    fn computed_signals_drop_dependencies_no_longer_read() {
+        use crate::Signal;
+
+        let flag = Signal::new(true);
+        let (a, b) = (Signal::new(1), Signal::new(10));
+        let (flag_, a_, b_) = (flag.clone(), a.clone(), b.clone());
+        let value = Signal::computed(move || if flag_.get() { a_.get() } else { b_.get() });
+        // Manual sources are kept even though they are never read
+        let manual = Signal::new(0);
+        manual.add_receiver(value.clone());
+
+        for flag_value in [false, true, false] {
+            flag.send(flag_value);
+            let (reads_a, reads_b) = (usize::from(flag_value), usize::from(!flag_value));
+            assert_eq!((a.receiver_count(), b.receiver_count()), (reads_a, reads_b));
+        }
+        a.send(2);
+        assert_eq!(value.update_count(), 3);
+        b.send(20);
+        assert_eq!(value.get(), 20);
+        assert_eq!(manual.receiver_count(), 1);
+        assert_eq!(flag.receiver_count(), 1);
+    }
+
+    #[test]
+    fn computed_signals_skip_untracked_reads() {
+        use crate::{untrack, Signal};
+
+        let (x, config) = (Signal::new(2), Signal::new(10));
+        let x_ = x.clone();
+        let lazy = Signal::driven_lazy(move || x_.get() + 1, |_, _| {});
+        x.add_receiver(lazy.clone());
+        let (lazy_, config_) = (lazy.clone(), config.clone());
+        let value = Signal::computed(move || lazy_.get() * untrack(|| config_.get()));
+
+        // `x` is read by the lazy processor on behalf of `lazy` only
+        assert_eq!(x.receiver_count(), 1);
+        assert_eq!(config.receiver_count(), 0);
+        x.send(4);
+        assert_eq!(value.get(), 50);
+    }
+
+    #[test]
+    fn deep_chains_stop_at_the_max_depth() {
+        use crate::api::{set_depth_hook, DEFAULT_MAX_DEPTH};
+        use std::sync::{Arc, Mutex};
+
+        let _max_depth = MAX_DEPTH.lock();
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let errors_ = errors.clone();
+        set_depth_hook(move |error| errors_.lock().unwrap().push(error.to_string()));
+        let head = signal!(0);
+        let mut chain = vec![head.clone()];
+        for _ in 0..DEFAULT_MAX_DEPTH + 10 {
+            let last = chain.last().unwrap().clone();
+            chain.push(signal!([last] last + 1));
+        }
+
+        head.send(1);
+        assert_eq!(chain[DEFAULT_MAX_DEPTH].get(), DEFAULT_MAX_DEPTH + 1);
+        // Past the limit, signals keep their initial values
+        assert_eq!(chain[DEFAULT_MAX_DEPTH + 1].get(), DEFAULT_MAX_DEPTH + 1);
+        let errors = errors.lock().unwrap();
+        assert!(errors.iter().any(|error| error.contains("signals deep")));
+    }
+
+    #[test]
+    fn long_chains_propagate_and_drop_on_a_small_stack() {
+        use crate::api::{set_max_depth, DEFAULT_MAX_DEPTH};
+
+        const LENGTH: usize = 10_000;
+        let _max_depth = MAX_DEPTH.lock();
+        set_max_depth(LENGTH + 1);
+        let propagate = || {
+            let head = signal!(0);
+            let mut chain = vec![head.clone()];
+            for _ in 0..LENGTH {
+                let last = chain.last().unwrap().clone();
+                chain.push(signal!([last] last + 1));
+            }
+            head.send(1);
+            assert_eq!(chain.last().unwrap().get(), LENGTH + 1);
+            // Each signal owns its source, so this frees the whole chain
+            // from within the drop of its last signal.
+            drop(head);
+            drop(chain);
+        };
+        let result = thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(propagate)
+            .unwrap()
+            .join();
+        set_max_depth(DEFAULT_MAX_DEPTH);
+        result.unwrap();
+    }
+
+    #[test]
+    fn filter_holds_last_passing_value() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let reading = signal!(0);
+        let high = reading.filter(|reading| *reading > 100);
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runs_ = runs.clone();
+        let _log = signal!([high] high; {
+            runs_.fetch_add(1, Ordering::SeqCst);
+        });
+
+        for value in [50, 150, 90, 200] {
+            reading.send(value);
+        }
+        assert_eq!(high.get(), 200);
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn effect_runs_until_detached() {
+        use std::sync::{Arc, Mutex};
+
+        let a = signal!(1);
+        let b = signal!(2);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_ = seen.clone();
+        let handle = effect!([a, b] seen_.lock().unwrap().push(a + b));
+        assert!(seen.lock().unwrap().is_empty());
+
+        a.send(10);
+        b.send(20);
+        handle.detach();
+        a.send(100);
+        assert_eq!(*seen.lock().unwrap(), vec![12, 30]);
+        assert_eq!(a.receiver_count(), 0);
+    }
+
+    #[test]
+    fn effects_reading_other_signals_do_not_deadlock() {
+        let a = signal!(0);
+        let b = signal!(0);
+        let (a_, b_) = (a.clone(), b.clone());
+        // Each effect reads the signal the other thread is sending to.
+        let _on_a = signal!([a] a; {
+            b_.get();
+        });
+        let _on_b = signal!([b] b; {
+            a_.get();
+        });
+
+        let handles = [a.clone(), b.clone()].map(|signal| {
+            thread::spawn(move || {
+                for value in 1..=200 {
+                    signal.send(value);
+                }
+            })
+        });
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!((a.get(), b.get()), (200, 200));
+    }
+
+    #[test]
+    #[should_panic(expected = "signal dependency cycle")]
+    fn cycles_panic_with_the_cycle() {
+        let a = signal!(1);
+        let b = signal!([a] a + 1);
+        let c = signal!([b] b + 1);
+        c.add_receiver(b.clone());
+
+        let err = a.send_checked(2).unwrap_err();
+        assert_eq!(err.cycle.len(), 3);
+        assert_eq!(err.cycle[0], err.cycle[2]);
+        a.send(2);
     }
 }
@@ -0,0 +1,79 @@
+#[cfg(feature = "parking_lot")]
+pub use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[cfg(not(feature = "parking_lot"))]
+pub use std_lock::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Locks of the standard library with the interface of `parking_lot`.
+///
+/// A lock poisoned by a panic is used as if nothing happened, as `parking_lot`
+/// does. Signal values are replaced in one step, so a panic in an effect or a
+/// processor never leaves one half updated.
+#[cfg(not(feature = "parking_lot"))]
+mod std_lock {
+    use std::sync::{self, MutexGuard, PoisonError, TryLockError};
+
+    pub use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+    /// A reader-writer lock that ignores poisoning.
+    #[derive(Debug, Default)]
+    pub struct RwLock<T: ?Sized>(sync::RwLock<T>);
+
+    impl<T> RwLock<T> {
+        pub const fn new(value: T) -> Self {
+            Self(sync::RwLock::new(value))
+        }
+    }
+
+    impl<T: ?Sized> RwLock<T> {
+        pub fn read(&self) -> RwLockReadGuard<'_, T> {
+            self.0.read().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        /// Locks for reading while the thread may already hold a read lock.
+        ///
+        /// The standard library makes no promise that this does not wait for
+        /// a writer queued in between, so reading a signal from the effect of
+        /// a deferred job can deadlock under contention, see
+        /// `sync::set_scheduler`.
+        pub fn read_recursive(&self) -> RwLockReadGuard<'_, T> {
+            self.read()
+        }
+
+        pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+            match self.0.try_read() {
+                Ok(guard) => Some(guard),
+                Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+                Err(TryLockError::WouldBlock) => None,
+            }
+        }
+
+        pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+            self.0.write().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+            match self.0.try_write() {
+                Ok(guard) => Some(guard),
+                Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+                Err(TryLockError::WouldBlock) => None,
+            }
+        }
+    }
+
+    /// A mutual exclusion lock that ignores poisoning.
+    #[derive(Debug, Default)]
+    pub struct Mutex<T: ?Sized>(sync::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub const fn new(value: T) -> Self {
+            Self(sync::Mutex::new(value))
+        }
+    }
+
+    impl<T: ?Sized> Mutex<T> {
+        pub fn lock(&self) -> MutexGuard<'_, T> {
+            self.0.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{api::SealedSignalTrait, lock::Mutex, sync::Signal};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+/// Number of values `Signal::stream` buffers before dropping the oldest.
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// Values received by a stream that have not been polled yet.
+struct Buffer<T> {
+    values: VecDeque<T>,
+    capacity: usize,
+    waker: Option<Waker>,
+}
+
+impl<T> Buffer<T> {
+    /// Stores a value, dropping the oldest one if the buffer is full, and
+    /// wakes the task waiting for it.
+    fn push(&mut self, value: T) {
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// An asynchronous stream of the values a `sync::Signal` is updated to.
+///
+/// Created by `Signal::stream` and `Signal::stream_with_capacity`. Each value
+/// sent to the signal after the stream was created is cloned into the buffer
+/// as it is stored, and kept until it is polled, so values sent from several
+/// threads at once each arrive once, in the order they were stored. When the
+/// buffer is full, the oldest value is dropped to make room, so a slow
+/// consumer always sees the most recent values.
+///
+/// `poll_next` has the signature of `futures::Stream::poll_next`, and `next`
+/// awaits the next value directly.
+///
+/// The stream keeps the signal alive, so it never ends. Drop it to stop
+/// listening.
+pub struct SignalStream<T: 'static> {
+    buffer: Arc<Mutex<Buffer<T>>>,
+    /// The signal filling the buffer
+    _source: Signal<T>,
+}
+
+impl<T: Clone + Send + Sync + 'static> SignalStream<T> {
+    pub(crate) fn new(source: &Signal<T>, capacity: usize) -> Self {
+        assert!(capacity > 0, "a signal stream needs room for one value");
+        let buffer = Arc::new(Mutex::new(Buffer {
+            values: VecDeque::new(),
+            capacity,
+            waker: None,
+        }));
+        let buffer_ = Arc::downgrade(&buffer);
+        source
+            .hooks()
+            .write()
+            .push(Box::new(move |value: &T| match buffer_.upgrade() {
+                Some(buffer) => {
+                    buffer.lock().push(value.clone());
+                    true
+                }
+                None => false,
+            }));
+        Self {
+            buffer,
+            _source: source.clone(),
+        }
+    }
+}
+
+impl<T: 'static> SignalStream<T> {
+    /// Polls for the next value, see `futures::Stream::poll_next`.
+    ///
+    /// The stream never ends, so this never returns `Poll::Ready(None)`.
+    pub fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut buffer = self.buffer.lock();
+        match buffer.values.pop_front() {
+            Some(value) => Poll::Ready(Some(value)),
+            None => {
+                buffer.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Waits for the next value.
+    // Named after `futures::StreamExt::next`, not `Iterator::next`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Next<'_, T> {
+        Next { stream: self }
+    }
+
+    /// Returns the number of values waiting to be polled.
+    pub fn len(&self) -> usize {
+        self.buffer.lock().values.len()
+    }
+
+    /// Returns whether no values are waiting to be polled.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Future returned by `SignalStream::next`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Next<'a, T: 'static> {
+    stream: &'a mut SignalStream<T>,
+}
+
+impl<T: 'static> Future for Next<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Pin::new(&mut *self.stream).poll_next(cx)
+    }
+}
@@ -1,6 +1,23 @@
-use crate::api::{Receptive, SealedSignalTrait};
-use parking_lot::RwLock;
-use std::sync::Arc;
+use crate::api::{self, Receptive, SealedSignalTrait, SignalTrait, State};
+use crate::lock::{Mutex, RwLock};
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    hash::{BuildHasher, Hash},
+    mem,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+pub use crate::api::{
+    batch, is_deterministic, propagation_order, send_all, set_deterministic, set_propagation_order,
+    Batch, Order,
+};
+#[cfg(feature = "futures")]
+use crate::{future::Changed, stream::SignalStream};
 
 /// A thread-safe reactive signal that can be observed and updated.
 ///
@@ -16,6 +33,7 @@ use std::sync::Arc;
 /// use std::thread;
 /// use reactivity::sync::Signal;
 /// use reactivity::signal;
+/// use reactivity::api::SignalTrait;
 ///
 /// // Create a thread-safe signal
 /// let count = signal!(0);
@@ -40,18 +58,126 @@ use std::sync::Arc;
 /// Use `sync::Signal` when signals need to be shared across multiple threads.
 /// If all signals will be accessed from the same thread, use `reactivity::Signal`
 /// instead for better performance.
-#[derive(Clone)]
-pub struct Signal<T> {
-    /// The current value of the signal
-    inner: Arc<RwLock<T>>,
-    /// Optional effect function called when the signal is updated
-    effect: Option<Arc<dyn Fn(&Signal<T>, &T) + Send + Sync>>,
-    /// Optional function that computes the signal's value
-    processor: Option<Arc<dyn Fn() -> T + Send + Sync>>,
-    /// List of receivers that depend on this signal
-    receivers: Arc<RwLock<Vec<Box<dyn Receptive + Send + Sync>>>>,
-    /// Counter tracking pending updates
-    dirty: Arc<RwLock<usize>>,
+///
+/// # Concurrent updates
+///
+/// An update propagates on the thread that sent it, and which receivers are
+/// still waiting for it is recorded on that thread too. Updates sent from
+/// several threads at once settle independently: every receiver reacts once
+/// for each of them, and no thread waits for another one's update, only for
+/// the lock of a value it reads or writes. The updates are not ordered
+/// against each other, though. A receiver recomputing for one of them may
+/// read values stored by another, and when two threads recompute the same
+/// receiver, the one finishing last keeps its value. Once the threads are
+/// done, the next update brings every receiver it reaches up to date.
+///
+/// # Memory
+///
+/// Like `reactivity::Signal`, creating a signal takes a single allocation
+/// holding its value and its graph state.
+pub struct Signal<T: 'static> {
+    /// State shared by all clones of the signal
+    state: Arc<State<Signal<T>>>,
+}
+
+// Implemented by hand, as deriving would require `T: Clone`.
+impl<T: 'static> Clone for Signal<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// A thread-safe handle to a receiver registered with `SignalTrait::subscribe`.
+///
+/// Dropping the handle unregisters the receiver. The handle is `Send + Sync` and
+/// does not keep the source signal alive.
+pub type Subscription = api::Subscription<dyn Receptive + Send + Sync>;
+
+/// A read-only view of a thread-safe `Signal`, see `SignalTrait::read_only`.
+pub type ReadSignal<T> = api::ReadSignal<Signal<T>>;
+
+/// A write-only handle to a thread-safe `Signal`, see `SignalTrait::split`.
+pub type WriteSignal<T> = api::WriteSignal<Signal<T>>;
+
+/// A handle to a thread-safe effect, see `Signal::effect`.
+pub type EffectHandle = api::EffectHandle<Signal<()>>;
+
+/// A thread-safe receiver running a function, see `FnReceiver::new`.
+pub type FnReceiver = api::FnReceiver<Signal<()>>;
+
+/// A collection of subscriptions and effects that detach together, see
+/// `api::ObserverScope`.
+pub type ObserverScope = api::ObserverScope<Signal<()>>;
+
+/// An effect run deferred by a scheduler, see `set_scheduler`.
+pub type Job = Box<dyn FnOnce() + Send>;
+
+/// Function given each effect to run, see `set_scheduler`.
+type Scheduler = Box<dyn Fn(Job) + Send + Sync>;
+
+static SCHEDULER: RwLock<Option<Scheduler>> = RwLock::new(None);
+
+/// Sets the function that runs the effects of thread-safe signals.
+///
+/// By default an effect runs inline, on the thread that sent the update,
+/// before the new value is stored. With a scheduler, each effect is handed to
+/// `scheduler` as a `Job` instead, which can run it later on another thread,
+/// such as the main thread of a GUI. Propagation itself stays on the sending
+/// thread, so derived values are still updated by `send`.
+///
+/// A job runs the effect with the value stored at the time the job runs, so
+/// an effect that reads its own signal sees the new value rather than the old
+/// one, and anything the effect writes elsewhere, for example another signal,
+/// is only visible after the scheduler has run it. A `get()` right after `send`
+/// may therefore still see the old value. Deferred effects hold a read lock on
+/// their signal while they run and must not send to it.
+///
+/// The scheduler applies to every thread-safe signal created afterwards and to
+/// existing ones from their next update. Call `clear_scheduler` to run effects
+/// inline again.
+///
+/// # Example
+/// ```
+/// use std::sync::mpsc;
+/// use reactivity::sync::{self, Signal};
+/// use reactivity::api::SignalTrait;
+///
+/// let (jobs, main) = mpsc::channel::<sync::Job>();
+/// let jobs = std::sync::Mutex::new(jobs);
+/// sync::set_scheduler(move |job| jobs.lock().unwrap().send(job).unwrap());
+///
+/// let count = Signal::new(0);
+/// let shown = Signal::new(0);
+/// let display = {
+///     let (source, shown) = (count.clone(), shown.clone());
+///     Signal::driven(move || source.get(), move |_, count| {
+///         shown.send(*count);
+///     })
+/// };
+/// count.add_receiver(display.clone());
+///
+/// let worker = count.clone();
+/// std::thread::spawn(move || {
+///     worker.send(5);
+/// })
+/// .join()
+/// .unwrap();
+/// assert_eq!(display.get(), 5);
+/// assert_eq!(shown.get(), 0);
+///
+/// // The main thread runs the effects
+/// main.try_iter().for_each(|job| job());
+/// assert_eq!(shown.get(), 5);
+/// ```
+pub fn set_scheduler(scheduler: impl Fn(Job) + Send + Sync + 'static) {
+    *SCHEDULER.write() = Some(Box::new(scheduler));
+}
+
+/// Runs the effects of thread-safe signals inline again, see `set_scheduler`.
+pub fn clear_scheduler() {
+    *SCHEDULER.write() = None;
 }
 
 impl<T: 'static> Signal<T> {
@@ -63,16 +189,23 @@ impl<T: 'static> Signal<T> {
     /// - `effect`: Side effect function called when the signal changes, receives both
     ///   the signal reference and the newly computed value
     ///
+    /// The effect runs before the new value is stored, so reading the signal
+    /// from inside it still gives the previous value. That no longer holds
+    /// for an effect deferred by a scheduler, see `set_scheduler`. Use
+    /// `driven_prev` to receive the previous value in either case.
+    ///
     /// # Example
     ///
     /// ```rust
     /// use std::thread;
     /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
     ///
     /// // Create a signal that reacts to changes in another signal
     /// let count = Signal::new(0);
+    /// let source = count.clone();
     /// let doubled = Signal::driven(
-    ///     || count.get() * 2,
+    ///     move || source.get() * 2,
     ///     |_, new_value| println!("Doubled value is now: {}", new_value)
     /// );
     ///
@@ -87,59 +220,1712 @@ impl<T: 'static> Signal<T> {
     /// ```
     pub fn driven<F>(processor: F, effect: impl Fn(&Signal<T>, &T) + Send + Sync + 'static) -> Self
     where
+        T: Send + Sync,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let value = processor();
+        Self::derived(value, move |_| Some(processor()), effect)
+    }
+
+    /// Creates a derived signal whose effect receives both the previous and
+    /// the new value.
+    ///
+    /// Works like `driven`, but the effect is called as
+    /// `effect(signal, old, new)`. When a scheduler defers the effect, both
+    /// values are cloned at the time of the update, so the job still sees
+    /// that update and not the value stored when it runs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(1);
+    /// let source = count.clone();
+    /// let delta = Signal::driven_prev(
+    ///     move || source.get(),
+    ///     |_, old, new| assert_eq!((*old, *new), (1, 3)),
+    /// );
+    /// count.add_receiver(delta.clone());
+    ///
+    /// count.send(3);
+    /// ```
+    pub fn driven_prev<F>(
+        processor: F,
+        effect: impl Fn(&Signal<T>, &T, &T) + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Clone + Send + Sync,
         F: Fn() -> T + Send + Sync + 'static,
     {
+        let effect = Arc::new(effect);
+        let scheduled = move |signal: &Signal<T>, new: &T| {
+            let scheduler = SCHEDULER.read();
+            let Some(scheduler) = &*scheduler else {
+                drop(scheduler);
+                return effect(signal, &signal.borrow(), new);
+            };
+            let (old, new) = (signal.get(), new.clone());
+            let (signal, effect) = (signal.clone(), effect.clone());
+            scheduler(Box::new(move || effect(&signal, &old, &new)));
+        };
+        let value = processor();
         Self::init(
-            Arc::new(RwLock::new(processor())),
-            Some(Arc::new(effect)),
-            Some(Arc::new(processor)),
-            Arc::new(RwLock::new(Vec::new())),
-            Arc::new(RwLock::new(0)),
+            value,
+            Some(Box::new(scheduled)),
+            Some(Box::new(move |_: &T| Some(processor()))),
         )
     }
-}
 
-impl<T: 'static> SealedSignalTrait for Signal<T> {
-    type Inner = T;
-    type Rc<U: ?Sized> = Arc<U>;
-    type Ptr<U> = RwLock<U>;
-    type Effect = dyn Fn(&Signal<T>, &T) + Send + Sync;
-    type Processor = dyn Fn() -> T + Send + Sync;
-    type Receiver = dyn Receptive + Send + Sync;
+    /// Creates a derived signal that computes each value from its previous
+    /// one.
+    ///
+    /// The signal starts at `initial`, and each update of a dependency calls
+    /// the processor with the current value to get the new one, which suits
+    /// feedback such as smoothing without a separate accumulator. The
+    /// previous value is only borrowed while the processor runs and is
+    /// replaced once it returns, so the processor may read this signal too,
+    /// but must not send to it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let input = Signal::new(10.0);
+    /// let source = input.clone();
+    /// let smoothed = Signal::driven_stateful(
+    ///     input.get(),
+    ///     move |previous| 0.5 * source.get() + 0.5 * previous,
+    ///     |_, _| {},
+    /// );
+    /// input.add_receiver(smoothed.clone());
+    ///
+    /// input.send(20.0);
+    /// assert_eq!(smoothed.get(), 15.0);
+    /// input.send(20.0);
+    /// assert_eq!(smoothed.get(), 17.5);
+    /// ```
+    pub fn driven_stateful<F>(
+        initial: T,
+        processor: F,
+        effect: impl Fn(&Signal<T>, &T) + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Send + Sync,
+        F: Fn(&T) -> T + Send + Sync + 'static,
+    {
+        Self::derived(initial, move |previous| Some(processor(previous)), effect)
+    }
 
-    fn init(
-        inner: Arc<RwLock<Self::Inner>>,
-        effect: Option<Arc<Self::Effect>>,
-        processor: Option<Arc<Self::Processor>>,
-        receivers: Arc<RwLock<Vec<Box<Self::Receiver>>>>,
-        dirty: Arc<RwLock<usize>>,
-    ) -> Self {
-        Self {
-            inner,
-            effect,
-            processor,
-            receivers,
-            dirty,
+    /// Creates a derived signal that computes its first value when it is
+    /// first needed.
+    ///
+    /// Works like `driven`, but the processor does not run until the value is
+    /// read, a receiver is added to the signal, or one of its dependencies is
+    /// updated. A signal that is never used never computes. The dependencies
+    /// captured by the processor must therefore still be alive at that point.
+    /// Until then the signal holds `T::default()`, which is never seen
+    /// through it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let runs = Arc::new(AtomicUsize::new(0));
+    /// let count = Signal::new(2);
+    /// let (source, runs_) = (count.clone(), runs.clone());
+    /// let doubled = Signal::driven_lazy(
+    ///     move || {
+    ///         runs_.fetch_add(1, Ordering::Relaxed);
+    ///         source.get() * 2
+    ///     },
+    ///     |_, _| {},
+    /// );
+    /// count.add_receiver(doubled.clone());
+    /// assert_eq!(runs.load(Ordering::Relaxed), 0);
+    ///
+    /// assert_eq!(doubled.get(), 4);
+    /// assert_eq!(runs.load(Ordering::Relaxed), 1);
+    /// ```
+    pub fn driven_lazy<F>(
+        processor: F,
+        effect: impl Fn(&Signal<T>, &T) + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Default + Send + Sync,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let signal = Self::derived(T::default(), move |_| Some(processor()), effect);
+        *signal.lazy().write() = true;
+        signal
+    }
+
+    /// Creates a derived signal whose effect also runs for the initial value.
+    ///
+    /// `driven` computes the initial value without running the effect, which
+    /// suits derived values but not watchers that should act on the current
+    /// state right away. This constructor runs the effect once as soon as the
+    /// signal is created. On that first run the stored value is already the
+    /// new one, so the `before` and `after` values of a `signal!` effect are
+    /// equal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(1);
+    /// let source = count.clone();
+    /// // Prints "count is 1" right away
+    /// let _watcher = Signal::driven_immediate(
+    ///     move || source.get(),
+    ///     |_, count| println!("count is {}", count),
+    /// );
+    /// ```
+    pub fn driven_immediate<F>(
+        processor: F,
+        effect: impl Fn(&Signal<T>, &T) + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Send + Sync,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let signal = Self::driven(processor, effect);
+        if let Some(effect) = signal.effect() {
+            // No one else holds the signal yet, so the effect may read it
+            // while the value is borrowed.
+            effect(&signal, &signal.borrow());
         }
+        signal
+    }
+
+    /// Creates a derived signal that only reacts when its value changes.
+    ///
+    /// Works like `driven`, but each recomputed value is compared with the stored
+    /// one. If they are equal, the effect is skipped and receivers are not
+    /// notified.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(1);
+    /// let source = count.clone();
+    /// let parity = Signal::driven_eq(
+    ///     move || source.get() % 2,
+    ///     |_, parity| println!("Parity changed to {}", parity)
+    /// );
+    /// count.add_receiver(parity.clone());
+    ///
+    /// // Prints nothing, the parity is still 1
+    /// count.send(3);
+    /// ```
+    pub fn driven_eq<F>(
+        processor: F,
+        effect: impl Fn(&Signal<T>, &T) + Send + Sync + 'static,
+    ) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: PartialEq + Send + Sync,
+    {
+        Self::driven_with_eq(processor, effect, T::eq)
+    }
+
+    /// Creates a derived signal that only reacts when `eq` considers its value
+    /// changed.
+    ///
+    /// Works like `driven_eq`, but `eq(old, new)` decides whether a recomputed
+    /// value is the same as the stored one. If it returns `true`, the stored
+    /// value is kept, the effect is skipped and receivers are not notified.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1.0_f64);
+    /// let source = reading.clone();
+    /// let smoothed = Signal::driven_with_eq(
+    ///     move || source.get(),
+    ///     |_, _| {},
+    ///     |old, new| (old - new).abs() < 0.1,
+    /// );
+    /// reading.add_receiver(smoothed.clone());
+    ///
+    /// reading.send(1.05);
+    /// assert_eq!(smoothed.get(), 1.0);
+    /// reading.send(1.5);
+    /// assert_eq!(smoothed.get(), 1.5);
+    /// ```
+    pub fn driven_with_eq<F>(
+        processor: F,
+        effect: impl Fn(&Signal<T>, &T) + Send + Sync + 'static,
+        eq: impl Fn(&T, &T) -> bool + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Send + Sync,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let value = processor();
+        Self::derived(
+            value,
+            move |current| {
+                let value = processor();
+                (!eq(current, &value)).then_some(value)
+            },
+            effect,
+        )
+    }
+
+    /// Creates a derived signal that survives panics in its processor.
+    ///
+    /// Works like `driven`, but a panic while recomputing is caught instead of
+    /// unwinding through the update. The signal keeps its previous value, the
+    /// effect is skipped and receivers are not notified, so other threads never
+    /// observe a half-computed value. The panic message is kept for
+    /// `last_error` until the next recomputation succeeds.
+    ///
+    /// The initial value is computed without catching, as there is no previous
+    /// value to fall back to. The panic hook still runs for caught panics.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let divisor = Signal::new(2);
+    /// let source = divisor.clone();
+    /// let quotient = Signal::driven_catching(move || 10 / source.get(), |_, _| {});
+    /// divisor.add_receiver(quotient.clone());
+    ///
+    /// divisor.send(0);
+    /// assert_eq!(quotient.get(), 5);
+    /// assert!(quotient.last_error().is_some());
+    ///
+    /// divisor.send(5);
+    /// assert_eq!(quotient.get(), 2);
+    /// assert_eq!(quotient.last_error(), None);
+    /// ```
+    pub fn driven_catching<F>(
+        processor: F,
+        effect: impl Fn(&Signal<T>, &T) + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Send + Sync,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let value = processor();
+        // The processor records panics on the signal it belongs to, which only
+        // exists once the processor has been moved into it.
+        let this = Arc::new(OnceLock::<api::WeakSignal<Self>>::new());
+        let this_ = this.clone();
+        let signal = Self::derived(
+            value,
+            move |_| {
+                let result = panic::catch_unwind(AssertUnwindSafe(&processor));
+                let error = result
+                    .as_ref()
+                    .err()
+                    .map(|payload| panic_message(&**payload));
+                if let Some(signal) = this_.get().and_then(|this| this.upgrade()) {
+                    *signal.error().write() = error;
+                }
+                result.ok()
+            },
+            effect,
+        );
+        let _ = this.set(signal.downgrade());
+        signal
+    }
+
+    /// Returns the message of the panic caught by the last recomputation, see
+    /// `driven_catching`.
+    ///
+    /// Returns `None` if the last recomputation succeeded, or if the signal was
+    /// not created with `driven_catching`.
+    pub fn last_error(&self) -> Option<String> {
+        self.error().read().clone()
+    }
+
+    /// Creates a signal with an initial value that recomputes with `processor`.
+    ///
+    /// The processor receives the current value and returns `None` to keep it
+    /// without running the effect or notifying receivers.
+    pub(crate) fn derived(
+        value: T,
+        processor: impl Fn(&T) -> Option<T> + Send + Sync + 'static,
+        effect: impl Fn(&Signal<T>, &T) + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: Send + Sync,
+    {
+        let effect = Arc::new(effect);
+        let scheduled = move |signal: &Signal<T>, value: &T| {
+            let scheduler = SCHEDULER.read();
+            let Some(scheduler) = &*scheduler else {
+                drop(scheduler);
+                return effect(signal, value);
+            };
+            let (signal, effect) = (signal.clone(), effect.clone());
+            scheduler(Box::new(move || effect(&signal, &signal.borrow())));
+        };
+        Self::init(value, Some(Box::new(scheduled)), Some(Box::new(processor)))
+    }
+
+    /// Creates a derived signal that applies `f` to each value of this signal.
+    ///
+    /// The new signal is registered as a receiver of this one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(2);
+    /// let label = count.map(|count| format!("{} items", count));
+    ///
+    /// count.send(3);
+    /// assert_eq!(label.get(), "3 items");
+    /// ```
+    pub fn map<U: Send + Sync + 'static, F>(&self, f: F) -> Signal<U>
+    where
+        T: Send + Sync,
+        F: Fn(&T) -> U + Send + Sync + 'static,
+    {
+        let source = self.clone();
+        let signal = Signal::driven(move || f(&source.borrow()), |_, _| {});
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal holding the part of each value picked by `f`,
+    /// passed on only when that part changes.
+    ///
+    /// `f` reads the value by reference, so the value of this signal is never
+    /// cloned, and the new signal compares each result with the last one, so
+    /// changes elsewhere in the value do not reach its receivers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// struct Config {
+    ///     log_level: u8,
+    ///     title: String,
+    /// }
+    ///
+    /// let config = Signal::new(Config { log_level: 1, title: "app".into() });
+    /// let log_level = config.project(|config| config.log_level);
+    ///
+    /// config.update(|config| config.title.push('!'));
+    /// assert_eq!(log_level.update_count(), 0);
+    ///
+    /// config.update(|config| config.log_level = 3);
+    /// assert_eq!(log_level.get(), 3);
+    /// ```
+    pub fn project<U, F>(&self, f: F) -> Signal<U>
+    where
+        T: Send + Sync,
+        U: PartialEq + Send + Sync + 'static,
+        F: Fn(&T) -> U + Send + Sync + 'static,
+    {
+        let source = self.clone();
+        let signal = Signal::derived(
+            self.with(&f),
+            move |last| {
+                let value = source.with(&f);
+                (value != *last).then_some(value)
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal converting each value of this signal with
+    /// `U::from`, see `map`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let small = Signal::new(7_u32);
+    /// let wide = small.coerce::<u64>();
+    ///
+    /// small.send(u32::MAX);
+    /// assert_eq!(wide.get(), u64::from(u32::MAX));
+    /// ```
+    pub fn coerce<U: From<T> + Send + Sync + 'static>(&self) -> Signal<U>
+    where
+        T: Clone + Send + Sync,
+    {
+        self.map(|value| U::from(value.clone()))
+    }
+
+    /// Creates a derived signal forwarding each value of this signal.
+    ///
+    /// The copy is a separate branch point: adapters such as `dedup` can be
+    /// chained on it without changing what other receivers of this signal
+    /// see.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1);
+    /// let changes = reading.cloned().dedup();
+    ///
+    /// reading.send(2);
+    /// reading.send(2);
+    /// assert_eq!(reading.update_count(), 2);
+    /// assert_eq!(changes.update_count(), 1);
+    /// ```
+    pub fn cloned(&self) -> Signal<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        self.map(T::clone)
+    }
+
+    /// Creates a derived signal forwarding each value of this signal
+    /// unchanged, calling `f` on each one first.
+    ///
+    /// `f` runs for every update of this signal, not for the current value,
+    /// which makes it a probe for logging or metrics in the middle of a chain.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1);
+    /// let doubled = reading
+    ///     .inspect(|reading| println!("reading: {reading}"))
+    ///     .map(|reading| reading * 2);
+    ///
+    /// reading.send(4);
+    /// assert_eq!(doubled.get(), 8);
+    /// ```
+    pub fn inspect(&self, f: impl Fn(&T) + Send + Sync + 'static) -> Signal<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        let source = self.clone();
+        let signal = Signal::derived(
+            self.get(),
+            move |_| Some(source.get()),
+            move |_, value| f(value),
+        );
+        self.add_receiver(signal.clone());
+        signal
     }
 
-    fn inner(&self) -> &Arc<RwLock<T>> {
-        &self.inner
+    /// Applies a transform built from other combinators to this signal.
+    ///
+    /// This is `f(self)`, written so that a pipeline kept in a function can
+    /// be applied to several sources and sit in the middle of a chain.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// fn positive_changes(signal: &Signal<i32>) -> Signal<i32> {
+    ///     signal.filter(|value| *value > 0).dedup()
+    /// }
+    ///
+    /// let reading = Signal::new(1);
+    /// let labels = reading
+    ///     .pipe(positive_changes)
+    ///     .map(|value| format!("{value} items"));
+    ///
+    /// reading.send(-2);
+    /// reading.send(3);
+    /// assert_eq!(labels.get(), "3 items");
+    /// ```
+    pub fn pipe<U: 'static>(&self, f: impl FnOnce(&Self) -> Signal<U>) -> Signal<U> {
+        f(self)
     }
 
-    fn effect(&self) -> Option<&Arc<Self::Effect>> {
-        self.effect.as_ref()
+    /// Creates a derived signal holding the values of both `a` and `b`.
+    ///
+    /// The new signal is registered as a receiver of both sources. When both
+    /// are updated in the same batch, it recomputes only once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::{api::SignalTrait, batch};
+    ///
+    /// let name = Signal::new("apples");
+    /// let count = Signal::new(2);
+    /// let both = Signal::combine(&name, &count);
+    ///
+    /// batch(|| {
+    ///     name.send("pears");
+    ///     count.send(3);
+    /// });
+    /// assert_eq!(both.get(), ("pears", 3));
+    /// ```
+    pub fn combine<U>(a: &Signal<T>, b: &Signal<U>) -> Signal<(T, U)>
+    where
+        T: Clone + Send + Sync,
+        U: Clone + Send + Sync + 'static,
+    {
+        let (a_, b_) = (a.clone(), b.clone());
+        let signal = Signal::driven(move || (a_.get(), b_.get()), |_, _| {});
+        a.add_receiver(signal.clone());
+        b.add_receiver(signal.clone());
+        signal
     }
 
-    fn processor(&self) -> Option<&Arc<Self::Processor>> {
-        self.processor.as_ref()
+    /// Combines this signal with `other`, see `Signal::combine`.
+    pub fn zip<U>(&self, other: &Signal<U>) -> Signal<(T, U)>
+    where
+        T: Clone + Send + Sync,
+        U: Clone + Send + Sync + 'static,
+    {
+        Signal::combine(self, other)
     }
 
-    fn receivers(&self) -> &Arc<RwLock<Vec<Box<Self::Receiver>>>> {
-        &self.receivers
+    /// Creates a derived signal holding the values of all `signals`, in order.
+    ///
+    /// The new signal is registered as a receiver of each source and
+    /// recomputes the whole list when any of them changes, once per batch
+    /// like `combine`. With no signals it holds an empty list for good.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let sensors: Vec<_> = (0..3).map(|_| Signal::new(0.0)).collect();
+    /// let readings = Signal::combine_all(&sensors);
+    ///
+    /// sensors[1].send(2.5);
+    /// assert_eq!(readings.get(), vec![0.0, 2.5, 0.0]);
+    /// ```
+    pub fn combine_all(signals: &[Signal<T>]) -> Signal<Vec<T>>
+    where
+        T: Clone + Send + Sync,
+    {
+        let sources = signals.to_vec();
+        let signal = Signal::driven(move || sources.iter().map(Signal::get).collect(), |_, _| {});
+        for source in signals {
+            source.add_receiver(signal.clone());
+        }
+        signal
+    }
+
+    /// Creates a derived signal that only takes values satisfying `predicate`.
+    ///
+    /// When the source changes to a value the predicate rejects, the filtered
+    /// signal keeps its last accepted value and its receivers are not notified.
+    /// It starts with the source's current value, whether or not it passes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(0);
+    /// let high = reading.filter(|reading| *reading > 100);
+    ///
+    /// reading.send(120);
+    /// reading.send(80);
+    /// assert_eq!(high.get(), 120);
+    /// ```
+    pub fn filter<F>(&self, predicate: F) -> Signal<T>
+    where
+        T: Clone + Send + Sync,
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        let source = self.clone();
+        let signal = Signal::derived(
+            self.get(),
+            move |_| {
+                let value = source.borrow();
+                predicate(&value).then(|| value.clone())
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal that skips consecutive duplicate values.
+    ///
+    /// Each value of this signal is compared with the last one forwarded, and
+    /// only a different value is taken and passed on to receivers. Unlike
+    /// `driven_eq`, this works on any signal, however it was built. The first
+    /// update after creation is always forwarded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1);
+    /// let changes = reading.dedup();
+    ///
+    /// reading.send(1);
+    /// reading.send(2);
+    /// reading.send(2);
+    /// assert_eq!(changes.update_count(), 2);
+    /// ```
+    pub fn dedup(&self) -> Signal<T>
+    where
+        T: Clone + PartialEq + Send + Sync,
+    {
+        let source = self.clone();
+        let first = AtomicBool::new(true);
+        let signal = Signal::derived(
+            self.get(),
+            move |last| {
+                let value = source.borrow();
+                (first.swap(false, Ordering::Relaxed) || *value != *last).then(|| value.clone())
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal that takes only every `n`-th update of this
+    /// one.
+    ///
+    /// The new signal starts at the current value, which does not count
+    /// towards the stride: it takes the values of the `n`-th, `2n`-th, and so
+    /// on update after its creation and keeps its value in between.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let frame = Signal::new(0);
+    /// let sampled = frame.every(3);
+    ///
+    /// (1..=7).for_each(|n| {
+    ///     frame.send(n);
+    /// });
+    /// assert_eq!(sampled.get(), 6);
+    /// assert_eq!(sampled.update_count(), 2);
+    /// ```
+    pub fn every(&self, n: usize) -> Signal<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        assert!(n > 0, "`every` needs a stride of at least one");
+        let source = self.clone();
+        let seen = AtomicUsize::new(0);
+        let signal = Signal::derived(
+            self.get(),
+            move |_| {
+                let count = seen.fetch_add(1, Ordering::Relaxed) + 1;
+                count.is_multiple_of(n).then(|| source.get())
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal holding the last `size` values of this one,
+    /// oldest first.
+    ///
+    /// The window starts with the current value and takes each update,
+    /// dropping the oldest value once it holds `size` of them, so it is
+    /// partial until enough values have arrived.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let reading = Signal::new(1);
+    /// let recent = reading.window(3);
+    /// let average = recent.map(|values| values.iter().sum::<i32>() / values.len() as i32);
+    ///
+    /// reading.send(5);
+    /// assert_eq!(recent.get(), [1, 5]);
+    /// (6..=8).for_each(|n| {
+    ///     reading.send(n);
+    /// });
+    /// assert_eq!(recent.get(), [6, 7, 8]);
+    /// assert_eq!(average.get(), 7);
+    /// ```
+    pub fn window(&self, size: usize) -> Signal<VecDeque<T>>
+    where
+        T: Clone + Send + Sync,
+    {
+        assert!(size > 0, "`window` needs a size of at least one");
+        let source = self.clone();
+        let signal = Signal::derived(
+            VecDeque::from([self.get()]),
+            move |window| {
+                let mut window = window.clone();
+                if window.len() == size {
+                    window.pop_front();
+                }
+                window.push_back(source.get());
+                Some(window)
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a derived signal that follows this one only while `enabled` is
+    /// true.
+    ///
+    /// The new signal starts at the current value and is registered as a
+    /// receiver of both signals. Updates arriving while `enabled` is false are
+    /// not taken, and when it flips back to true the signal catches up with
+    /// the current value of this one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let input = Signal::new(1);
+    /// let live = Signal::new(true);
+    /// let preview = input.gated_by(&live);
+    ///
+    /// live.send(false);
+    /// input.send(2);
+    /// assert_eq!(preview.get(), 1);
+    ///
+    /// live.send(true);
+    /// assert_eq!(preview.get(), 2);
+    /// ```
+    pub fn gated_by(&self, enabled: &Signal<bool>) -> Signal<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        let (source, enabled_) = (self.clone(), enabled.clone());
+        let signal = Signal::derived(
+            self.get(),
+            move |_| enabled_.get().then(|| source.get()),
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        enabled.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a signal taking the value of whichever of `signals` updated
+    /// last.
+    ///
+    /// The new signal starts at the value of the first signal. Each of
+    /// `signals` gets a listener that sends its new values on to the merged
+    /// signal, owned by that signal so it lasts as long as the source does.
+    /// When several sources update in one propagation, for example inside a
+    /// `batch`, each value is sent on in turn, and the source whose listener
+    /// runs last in propagation order wins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `signals` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let keyboard = Signal::new("none");
+    /// let mouse = Signal::new("none");
+    /// let last_input = Signal::merge(&[keyboard.clone(), mouse.clone()]);
+    ///
+    /// mouse.send("click");
+    /// assert_eq!(last_input.get(), "click");
+    /// keyboard.send("enter");
+    /// assert_eq!(last_input.get(), "enter");
+    /// ```
+    pub fn merge(signals: &[Signal<T>]) -> Signal<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        let first = signals.first().expect("`merge` needs at least one signal");
+        let merged = Signal::new(first.get());
+        for signal in signals {
+            let (source, target) = (signal.downgrade(), merged.downgrade());
+            let listener = Signal::derived(
+                (),
+                |_| Some(()),
+                move |_, _| {
+                    if let (Some(source), Some(target)) = (source.upgrade(), target.upgrade()) {
+                        drop(target.send(source.get()));
+                    }
+                },
+            );
+            let listener: Box<dyn Receptive + Send + Sync> = Box::new(listener);
+            signal.add_receiver(listener);
+        }
+        merged
+    }
+
+    /// Keeps `a` and `b` in sync in both directions.
+    ///
+    /// `b` first takes `to` of the value of `a`. After that each update of
+    /// `a` sends `to` of its value to `b`, and each update of `b` sends `from`
+    /// of its value to `a`. A forwarded value equal to the current one is not
+    /// sent, which ends the exchange, so `from` and `to` have to agree:
+    /// `to(&from(&to(&x)))` must equal `to(&x)`, or the two keep updating
+    /// each other until `api::max_depth` rounds. The listeners are owned by
+    /// the signals and last as long as both do.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let celsius = Signal::new(100.0);
+    /// let fahrenheit = Signal::new(0.0);
+    /// Signal::bind(&celsius, &fahrenheit, |c| c * 9.0 / 5.0 + 32.0, |f| (f - 32.0) * 5.0 / 9.0);
+    /// assert_eq!(fahrenheit.get(), 212.0);
+    ///
+    /// fahrenheit.send(32.0);
+    /// assert_eq!(celsius.get(), 0.0);
+    /// ```
+    pub fn bind<U>(
+        a: &Signal<T>,
+        b: &Signal<U>,
+        to: impl Fn(&T) -> U + Send + Sync + 'static,
+        from: impl Fn(&U) -> T + Send + Sync + 'static,
+    ) where
+        T: PartialEq + Send + Sync,
+        U: PartialEq + Send + Sync + 'static,
+    {
+        b.set_if_changed(a.with(&to));
+        let (source, target) = (a.downgrade(), b.downgrade());
+        let forward = Signal::derived(
+            (),
+            |_| Some(()),
+            move |_, _| {
+                if let (Some(source), Some(target)) = (source.upgrade(), target.upgrade()) {
+                    target.set_if_changed(source.with(&to));
+                }
+            },
+        );
+        let (source, target) = (b.downgrade(), a.downgrade());
+        let backward = Signal::derived(
+            (),
+            |_| Some(()),
+            move |_, _| {
+                if let (Some(source), Some(target)) = (source.upgrade(), target.upgrade()) {
+                    target.set_if_changed(source.with(&from));
+                }
+            },
+        );
+        let forward: Box<dyn Receptive + Send + Sync> = Box::new(forward);
+        let backward: Box<dyn Receptive + Send + Sync> = Box::new(backward);
+        a.add_receiver(forward);
+        b.add_receiver(backward);
+    }
+
+    /// Creates a signal taking the value of `value` each time `trigger` updates.
+    ///
+    /// Only `trigger` gets the new signal as a receiver. `value` is peeked when
+    /// the trigger fires, so its own changes are not passed on until then.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let clicks = Signal::new(0);
+    /// let text = Signal::new("draft");
+    /// let submitted = Signal::sample(&clicks, &text);
+    ///
+    /// text.send("final");
+    /// assert_eq!(submitted.get(), "draft");
+    ///
+    /// clicks.send(1);
+    /// assert_eq!(submitted.get(), "final");
+    /// ```
+    pub fn sample<U>(trigger: &Signal<U>, value: &Signal<T>) -> Signal<T>
+    where
+        T: Clone + Send + Sync,
+        U: Send + Sync + 'static,
+    {
+        let value = value.clone();
+        let signal = Signal::driven(move || value.peek(), |_, _| {});
+        trigger.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Creates a signal that keeps the latest `Some` value of `source`.
+    ///
+    /// The new signal starts at `initial` and is registered as a receiver of
+    /// `source`. Each time `source` updates to `Some(value)` it takes `value`,
+    /// while updates to `None` are ignored. If `source` already holds a value
+    /// when `hold` is called, the signal starts at that value and `initial` is
+    /// never used. Mapping an `Async` signal with `Async::ready` gives a
+    /// placeholder until a future completes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let clicked = Signal::new(None);
+    /// let last = Signal::hold("nothing yet", &clicked);
+    /// assert_eq!(last.get(), "nothing yet");
+    ///
+    /// clicked.send(Some("ok"));
+    /// clicked.send(None);
+    /// assert_eq!(last.get(), "ok");
+    /// ```
+    pub fn hold(initial: T, source: &Signal<Option<T>>) -> Signal<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        let value = source.peek().unwrap_or(initial);
+        let source_ = source.clone();
+        let signal = Signal::derived(value, move |_| source_.peek(), |_, _| {});
+        source.add_receiver(signal.clone());
+        signal
+    }
+
+    /// Converts this signal into a single-threaded `reactivity::Signal`.
+    ///
+    /// Only the current value carries over, see `reactivity::Signal::into_sync`
+    /// for the reverse direction.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(3);
+    /// let local = count.into_local();
+    /// assert_eq!(local.get(), 3);
+    /// ```
+    pub fn into_local(self) -> crate::Signal<T>
+    where
+        T: Clone,
+    {
+        crate::Signal::new(self.get())
+    }
+
+    /// Sends a value unless that would block on the lock of this signal.
+    ///
+    /// If another thread holds the value locked, `value` is handed back right
+    /// away and nothing is propagated, so a thread that must never wait, such
+    /// as an audio callback, can drop the update instead. Only the lock of
+    /// this signal is tried: once the value is stored, notifying the
+    /// receivers takes their locks as `send` does and may still wait on
+    /// them. The update has settled by the time this returns. A constant
+    /// ignores the value, as with `send`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let level = Signal::new(0.0);
+    /// let reading = level.borrow();
+    /// assert_eq!(level.try_send(0.5), Err(0.5));
+    ///
+    /// drop(reading);
+    /// assert_eq!(level.try_send(0.5), Ok(()));
+    /// assert_eq!(level.get(), 0.5);
+    /// ```
+    pub fn try_send(&self, value: T) -> Result<(), T> {
+        if self.is_constant() {
+            return Ok(());
+        }
+        let Some(mut inner) = self.inner().try_write() else {
+            return Err(value);
+        };
+        let old = mem::replace(&mut *inner, value);
+        self.stored(&inner);
+        drop(inner);
+        drop(old);
+        drop(api::propagate(self));
+        Ok(())
+    }
+
+    /// Calls `f` with the previous and the new value on each update of this
+    /// signal.
+    ///
+    /// `f` is not called for the current value. On the first update it
+    /// receives the value this signal had when `watch` was called as the
+    /// previous one, and after that the value of the update before. The
+    /// listener is owned by this signal and removed when the returned
+    /// subscription is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let temperature = Signal::new(20);
+    /// let _watch = temperature.watch(|old, new| println!("{old} -> {new}"));
+    ///
+    /// // Prints "20 -> 23"
+    /// temperature.send(23);
+    /// ```
+    pub fn watch(&self, f: impl Fn(&T, &T) + Send + Sync + 'static) -> Subscription
+    where
+        T: Clone + Send + Sync,
+    {
+        let last = Mutex::new(self.get());
+        let source = self.downgrade();
+        let listener = Signal::derived(
+            (),
+            |_| Some(()),
+            move |_, _| {
+                if let Some(source) = source.upgrade() {
+                    let new = source.get();
+                    let old = mem::replace(&mut *last.lock(), new.clone());
+                    f(&old, &new);
+                }
+            },
+        );
+        let listener: Box<dyn Receptive + Send + Sync> = Box::new(listener);
+        self.subscribe(listener)
+    }
+
+    /// Runs `f` on each update of this signal until the returned subscription
+    /// is dropped.
+    ///
+    /// This is a `FnReceiver` added with `subscribe`, so the signal owns the
+    /// listener and nothing else has to be kept alive.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(0);
+    /// let changed = count.on_change(|| println!("count changed"));
+    ///
+    /// // Prints "count changed"
+    /// count.send(1);
+    /// drop(changed);
+    /// assert_eq!(count.receiver_count(), 0);
+    /// ```
+    pub fn on_change(&self, f: impl Fn() + Send + Sync + 'static) -> Subscription
+    where
+        T: Send + Sync,
+    {
+        self.subscribe(FnReceiver::new(f))
+    }
+
+    /// Runs `f` with the new value on each update of this signal until the
+    /// returned subscription is dropped.
+    ///
+    /// Receivers are only told that a source changed, so the listener keeps
+    /// a clone of each value as it is stored and passes those to `f`. Every
+    /// value sent reaches `f` once, in the order the values were stored, even
+    /// when several threads send at once. Only one thread runs `f` at a time,
+    /// whichever finds the values first, and `f` may read or send to this
+    /// signal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let path = Signal::new(String::from("/"));
+    /// let _route = path.on_value(|path| println!("navigated to {path}"));
+    ///
+    /// // Prints "navigated to /settings"
+    /// path.send("/settings".into());
+    /// ```
+    pub fn on_value(&self, f: impl Fn(&T) + Send + Sync + 'static) -> Subscription
+    where
+        T: Clone + Send + Sync,
+    {
+        let snapshots = Arc::new(RwLock::new((VecDeque::new(), false)));
+        let queue = Arc::downgrade(&snapshots);
+        self.hooks()
+            .write()
+            .push(Box::new(move |value: &T| match queue.upgrade() {
+                Some(queue) => {
+                    queue.write().0.push_back(value.clone());
+                    true
+                }
+                None => false,
+            }));
+        self.on_change(move || api::deliver(&*snapshots, &f))
+    }
+
+    /// Runs `f` with the next value of this signal, then stops listening.
+    ///
+    /// The listener removes itself from the receivers once `f` has run. The
+    /// update in flight keeps working from its own snapshot of the receivers,
+    /// so siblings are not skipped. The returned handle keeps the listener
+    /// alive; dropping it before the next update cancels the call.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let ready = Signal::new(false);
+    /// let _once = ready.subscribe_once(|ready| println!("ready: {}", ready));
+    ///
+    /// // Prints "ready: true"
+    /// ready.send(true);
+    /// // Prints nothing
+    /// ready.send(false);
+    /// assert_eq!(ready.receiver_count(), 0);
+    /// ```
+    pub fn subscribe_once(&self, f: impl FnOnce(&T) + Send + 'static) -> EffectHandle
+    where
+        T: Send + Sync,
+    {
+        let f = Mutex::new(Some(f));
+        let source = self.downgrade();
+        let listener = Signal::derived(
+            (),
+            |_| Some(()),
+            move |listener, _| {
+                let Some(source) = source.upgrade() else {
+                    return;
+                };
+                if let Some(f) = f.lock().take() {
+                    source.with(f);
+                }
+                source.remove_receiver(listener);
+            },
+        );
+        self.add_receiver(listener.clone());
+        EffectHandle::new(listener)
+    }
+
+    /// Runs `f` once this signal is dropped, on the thread dropping it.
+    ///
+    /// Clones of a signal share its state, so `f` runs when the last clone is
+    /// dropped, whichever thread that happens on, not when this handle is.
+    /// Receiver lists do not keep the signal alive, but the processors of
+    /// signals derived from it usually hold clones. Functions run in the order
+    /// they were registered, after the signal has detached from its sources.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let buffer = Signal::new(vec![0u8; 1024]);
+    /// buffer.on_drop(|| println!("buffer released"));
+    ///
+    /// let clone = buffer.clone();
+    /// drop(buffer);
+    /// // Prints "buffer released"
+    /// drop(clone);
+    /// ```
+    pub fn on_drop(&self, f: impl FnOnce() + Send + Sync + 'static) {
+        self.cleanups().write().push(Box::new(f));
+    }
+
+    /// Records each value this signal is updated to, for assertions in tests.
+    ///
+    /// The returned vec starts empty and gets a clone of every new value once
+    /// it is stored. The listener doing so is owned by this signal, so values
+    /// are recorded for as long as the signal lives. Only available with the
+    /// `testing` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::{signal, sync::Signal};
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = signal!(0);
+    /// let doubled = signal!([count] count * 2);
+    /// let history = doubled.collect();
+    ///
+    /// count.send(1);
+    /// count.send(2);
+    /// assert_eq!(*history.lock().unwrap(), vec![2, 4]);
+    /// ```
+    #[cfg(feature = "testing")]
+    pub fn collect(&self) -> Arc<std::sync::Mutex<Vec<T>>>
+    where
+        T: Clone + Send + Sync,
+    {
+        let history = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (source, values) = (self.downgrade(), history.clone());
+        let listener = Signal::derived(
+            (),
+            |_| Some(()),
+            move |_, _| {
+                if let Some(source) = source.upgrade() {
+                    values.lock().unwrap().push(source.get());
+                }
+            },
+        );
+        let listener: Box<dyn Receptive + Send + Sync> = Box::new(listener);
+        self.add_receiver(listener);
+        history
+    }
+
+    /// Creates a signal that steps through `values`, for deterministic tests.
+    ///
+    /// The signal starts with the first value. Each call of the returned
+    /// stepper sends the next one and returns `true`, or returns `false` once
+    /// every value has been sent. Only available with the `testing` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::{signal, sync::Signal};
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let (reading, mut step) = Signal::replay(vec![1, 5, 3]);
+    /// let peak = signal!([reading] reading > 4);
+    ///
+    /// assert!(step());
+    /// assert!(peak.get());
+    /// assert!(step());
+    /// assert!(!step());
+    /// assert_eq!(reading.get(), 3);
+    /// ```
+    #[cfg(feature = "testing")]
+    pub fn replay(values: Vec<T>) -> (Signal<T>, impl FnMut() -> bool + Send)
+    where
+        T: Send + Sync,
+    {
+        let mut values = values.into_iter();
+        let signal = Signal::new(values.next().expect("`replay` needs at least one value"));
+        let source = signal.clone();
+        let step = move || match values.next() {
+            Some(value) => {
+                source.send(value);
+                true
+            }
+            None => false,
+        };
+        (signal, step)
+    }
+
+    /// Returns a stream of the values this signal is updated to, buffering up
+    /// to `stream::DEFAULT_CAPACITY` of them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::{future::Future, pin::pin, task::{Context, Poll, Waker}};
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(0);
+    /// let mut changes = count.stream();
+    ///
+    /// count.send(1);
+    /// count.send(2);
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// assert_eq!(pin!(changes.next()).poll(&mut cx), Poll::Ready(Some(1)));
+    /// assert_eq!(pin!(changes.next()).poll(&mut cx), Poll::Ready(Some(2)));
+    /// assert_eq!(pin!(changes.next()).poll(&mut cx), Poll::Pending);
+    /// ```
+    #[cfg(feature = "futures")]
+    pub fn stream(&self) -> SignalStream<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        self.stream_with_capacity(crate::stream::DEFAULT_CAPACITY)
+    }
+
+    /// Returns a stream of the values this signal is updated to, buffering up
+    /// to `capacity` of them.
+    ///
+    /// Once `capacity` values are waiting, each new value drops the oldest one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    #[cfg(feature = "futures")]
+    pub fn stream_with_capacity(&self, capacity: usize) -> SignalStream<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        SignalStream::new(self, capacity)
+    }
+
+    /// Returns a future resolving to the next value this signal is updated to.
+    ///
+    /// The future listens from the moment it is created, so an update sent
+    /// before it is first polled still resolves it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::{future::Future, pin::pin, task::{Context, Poll, Waker}};
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(0);
+    /// let changed = count.changed();
+    ///
+    /// count.send(1);
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// assert_eq!(pin!(changed).poll(&mut cx), Poll::Ready(1));
+    /// ```
+    #[cfg(feature = "futures")]
+    pub fn changed(&self) -> Changed<T>
+    where
+        T: Clone + Send + Sync,
+    {
+        Changed::new(self)
+    }
+}
+
+impl<T: 'static> ReadSignal<T> {
+    /// Creates a derived signal that applies `f` to each value, see
+    /// `Signal::map`.
+    pub fn map<U: Send + Sync + 'static, F>(&self, f: F) -> Signal<U>
+    where
+        T: Send + Sync,
+        F: Fn(&T) -> U + Send + Sync + 'static,
+    {
+        self.signal.map(f)
+    }
+}
+
+impl Signal<()> {
+    /// Creates an effect that runs `f` each time one of its dependencies
+    /// changes.
+    ///
+    /// Unlike a derived signal, the effect holds no value and `f` is not run
+    /// on creation. Register `EffectHandle::observer` as a receiver of each
+    /// dependency, or use the `effect!` macro to do both at once. The effect
+    /// lives until the handle is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(0);
+    /// let source = count.clone();
+    /// let handle = Signal::effect(move || println!("count is {}", source.get()));
+    /// count.add_receiver(handle.observer().clone());
+    ///
+    /// // Prints "count is 1"
+    /// count.send(1);
+    ///
+    /// // Prints nothing, the effect is detached
+    /// drop(handle);
+    /// count.send(2);
+    /// ```
+    pub fn effect(f: impl Fn() + Send + Sync + 'static) -> EffectHandle {
+        EffectHandle::new(Signal::derived(
+            (),
+            move |_| {
+                f();
+                Some(())
+            },
+            |_, _| {},
+        ))
+    }
+}
+
+impl ObserverScope {
+    /// Calls `f` with the previous and the new value on each update of
+    /// `signal` until the scope is dropped, see `Signal::watch`.
+    pub fn watch<T: Clone + Send + Sync + 'static>(
+        &mut self,
+        signal: &Signal<T>,
+        f: impl Fn(&T, &T) + Send + Sync + 'static,
+    ) {
+        self.add(signal.watch(f));
+    }
+}
+
+impl FnReceiver {
+    /// Wraps `f` as a receiver that runs it on each update of the signals it
+    /// is added to.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::{FnReceiver, Signal};
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let count = Signal::new(0);
+    /// // Prints "count changed" on each update
+    /// count.add_receiver(FnReceiver::new(|| println!("count changed")));
+    /// count.send(1);
+    /// ```
+    pub fn new(f: impl Fn() + Send + Sync + 'static) -> Self {
+        api::FnReceiver::from_observer(Signal::derived(
+            (),
+            move |_| {
+                f();
+                Some(())
+            },
+            |_, _| {},
+        ))
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Signal<Signal<T>> {
+    /// Creates a signal following whichever signal this one currently holds.
+    ///
+    /// The flattened signal takes the value of the inner signal, and updates
+    /// when either the inner signal changes or this signal switches to another
+    /// one. On a switch, it stops listening to the previous inner signal, so
+    /// late updates of that signal are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let local = Signal::new("local");
+    /// let remote = Signal::new("remote");
+    /// let source = Signal::new(local.clone());
+    /// let data = source.flatten();
+    ///
+    /// source.send(remote.clone());
+    /// assert_eq!(data.get(), "remote");
+    ///
+    /// local.send("stale");
+    /// assert_eq!(data.get(), "remote");
+    /// remote.send("fresh");
+    /// assert_eq!(data.get(), "fresh");
+    /// ```
+    pub fn flatten(&self) -> Signal<T> {
+        // The inner signal currently listened to, by id
+        let current: Arc<Mutex<Option<(usize, Subscription)>>> = Arc::new(Mutex::new(None));
+        let (outer, outer_, current_) = (self.clone(), self.clone(), current.clone());
+        let signal = Signal::driven(
+            move || outer.borrow().get(),
+            move |signal, _| {
+                let inner = outer_.get();
+                let mut current = current_.lock();
+                if current.as_ref().map(|(id, _)| *id) != Some(inner.id()) {
+                    *current = Some((inner.id(), inner.subscribe(signal.clone())));
+                }
+            },
+        );
+        self.add_receiver(signal.clone());
+        let inner = self.get();
+        *current.lock() = Some((inner.id(), inner.subscribe(signal.clone())));
+        signal
+    }
+}
+
+impl<T: 'static, E: 'static> Signal<Result<T, E>> {
+    /// Creates a derived signal applying `f` to successful values.
+    ///
+    /// Errors are passed on unchanged without calling `f`, so while this signal
+    /// holds an error the derived signal holds the same error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let input = Signal::new("4".parse::<i32>());
+    /// let squared = input.map_ok(|n| n * n);
+    /// assert_eq!(squared.get(), Ok(16));
+    ///
+    /// input.send("x".parse::<i32>());
+    /// assert!(squared.get().is_err());
+    /// ```
+    pub fn map_ok<U: Send + Sync + 'static, F>(&self, f: F) -> Signal<Result<U, E>>
+    where
+        T: Send + Sync,
+        E: Clone + Send + Sync,
+        F: Fn(&T) -> U + Send + Sync + 'static,
+    {
+        self.map(move |result| match result {
+            Ok(value) => Ok(f(value)),
+            Err(error) => Err(error.clone()),
+        })
+    }
+
+    /// Creates a derived signal applying `f` to errors.
+    ///
+    /// Successful values are passed on unchanged without calling `f`.
+    pub fn map_err<G: Send + Sync + 'static, F>(&self, f: F) -> Signal<Result<T, G>>
+    where
+        T: Clone + Send + Sync,
+        E: Send + Sync,
+        F: Fn(&E) -> G + Send + Sync + 'static,
+    {
+        self.map(move |result| match result {
+            Ok(value) => Ok(value.clone()),
+            Err(error) => Err(f(error)),
+        })
+    }
+
+    /// Creates a derived signal applying the fallible `f` to successful values.
+    ///
+    /// Like `map_ok`, an error in this signal is passed on without calling `f`.
+    /// An error returned by `f` is held the same way, so errors flow down a
+    /// chain of `and_then` signals until a value succeeds again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let input = Signal::new(Ok::<_, String>(8));
+    /// let halved = input.and_then(|n| {
+    ///     if n % 2 == 0 {
+    ///         Ok(n / 2)
+    ///     } else {
+    ///         Err(format!("{n} is odd"))
+    ///     }
+    /// });
+    /// assert_eq!(halved.get(), Ok(4));
+    ///
+    /// input.send(Ok(3));
+    /// assert_eq!(halved.get(), Err("3 is odd".to_string()));
+    /// ```
+    pub fn and_then<U: Send + Sync + 'static, F>(&self, f: F) -> Signal<Result<U, E>>
+    where
+        T: Send + Sync,
+        E: Clone + Send + Sync,
+        F: Fn(&T) -> Result<U, E> + Send + Sync + 'static,
+    {
+        self.map(move |result| match result {
+            Ok(value) => f(value),
+            Err(error) => Err(error.clone()),
+        })
+    }
+}
+
+/// Extracts the message of a caught panic.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "processor panicked".to_string()
+    }
+}
+
+impl<K, V, H> Signal<HashMap<K, V, H>>
+where
+    K: Eq + Hash + Send + Sync + 'static,
+    V: Clone + PartialEq + Send + Sync + 'static,
+    H: BuildHasher + Send + Sync + 'static,
+{
+    /// Creates a signal following the value at `key`.
+    ///
+    /// The new signal is registered as a receiver of the map and holds
+    /// `Some` value while `key` is present and `None` otherwise. It checks the
+    /// key on each update of the map, but only passes the update on when the
+    /// value at the key changed, including when the key is removed or added
+    /// back, so observers of one key are not run for changes to another.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let scores = Signal::new(HashMap::from([("ada", 3)]));
+    /// let ada = scores.key_signal("ada");
+    ///
+    /// scores.update(|scores| {
+    ///     scores.insert("grace", 5);
+    /// });
+    /// assert_eq!(ada.update_count(), 0);
+    ///
+    /// scores.update(|scores| {
+    ///     scores.remove("ada");
+    /// });
+    /// assert_eq!(ada.get(), None);
+    /// ```
+    pub fn key_signal(&self, key: K) -> Signal<Option<V>> {
+        let source = self.clone();
+        let signal = Signal::derived(
+            self.with(|map| map.get(&key).cloned()),
+            move |last| {
+                let value = source.with(|map| map.get(&key).cloned());
+                (value != *last).then_some(value)
+            },
+            |_, _| {},
+        );
+        self.add_receiver(signal.clone());
+        signal
+    }
+}
+
+impl_ops!(Send, Sync);
+
+impl<T: 'static> SealedSignalTrait for Signal<T> {
+    type Inner = T;
+    type Rc<U: ?Sized> = Arc<U>;
+    type Ptr<U> = RwLock<U>;
+    type Effect = dyn Fn(&Signal<T>, &T) + Send + Sync;
+    type Processor = dyn Fn(&T) -> Option<T> + Send + Sync;
+    type Receiver = dyn Receptive + Send + Sync;
+    type Cleanup = dyn FnOnce() + Send + Sync;
+    type Hook = dyn Fn(&T) -> bool + Send + Sync;
+
+    fn from_state(state: Arc<State<Self>>) -> Self {
+        Self { state }
     }
 
-    fn dirty(&self) -> &Arc<RwLock<usize>> {
-        &self.dirty
+    fn state(&self) -> &Arc<State<Self>> {
+        &self.state
     }
 }
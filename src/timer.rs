@@ -0,0 +1,276 @@
+use crate::{
+    api::{SignalTrait, WeakSignal},
+    lock::Mutex,
+    sync::Signal,
+};
+use std::{
+    mem,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+/// A callback handed to a scheduling function, to be run once its delay has
+/// passed.
+pub type Callback = Box<dyn FnOnce() + Send>;
+
+/// A function that runs a callback after a delay.
+///
+/// Timers are left to the caller, so any runtime can drive them. A scheduler
+/// must not run the callback before returning, since signals are still being
+/// updated at that point.
+type Schedule = Box<dyn Fn(Duration, Callback) + Send + Sync>;
+
+/// State shared by a rate limited signal and its pending callbacks.
+struct Limiter<T: 'static, S> {
+    source: Signal<T>,
+    /// The rate limited signal, set once it has been created
+    target: OnceLock<WeakSignal<Signal<T>>>,
+    duration: Duration,
+    schedule: Schedule,
+    state: S,
+}
+
+impl<T: Clone + Send + Sync + 'static, S: Send + Sync + 'static> Limiter<T, S> {
+    fn new(
+        source: &Signal<T>,
+        duration: Duration,
+        schedule: impl Fn(Duration, Callback) + Send + Sync + 'static,
+        state: S,
+    ) -> Arc<Self> {
+        Arc::new(Limiter {
+            source: source.clone(),
+            target: OnceLock::new(),
+            duration,
+            schedule: Box::new(schedule),
+            state,
+        })
+    }
+
+    /// Runs `f` once the duration has passed.
+    fn after(self: &Arc<Self>, f: impl FnOnce(Arc<Self>) + Send + 'static) {
+        let limiter = self.clone();
+        (self.schedule)(self.duration, Box::new(move || f(limiter)));
+    }
+
+    /// Sends the latest value of the source to the rate limited signal.
+    fn emit(&self) {
+        if let Some(target) = self.target.get().and_then(WeakSignal::upgrade) {
+            drop(target.send(self.source.get()));
+        }
+    }
+
+    /// Creates the rate limited signal, recomputed by `processor`.
+    fn attach(
+        self: Arc<Self>,
+        processor: impl Fn(&Arc<Self>) -> Option<T> + Send + Sync + 'static,
+    ) -> Signal<T> {
+        let limiter = self.clone();
+        let signal = Signal::derived(self.source.get(), move |_| processor(&limiter), |_, _| {});
+        let _ = self.target.set(signal.downgrade());
+        self.source.add_receiver(signal.clone());
+        signal
+    }
+}
+
+/// The edge of a burst of updates at which a debounced signal takes a value,
+/// see `Signal::debounce_edge`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Edge {
+    /// Takes the first update of a burst right away and ignores the rest.
+    Leading,
+    /// Takes the latest value once the burst is over.
+    #[default]
+    Trailing,
+    /// Takes the first update right away and, if more followed it, the
+    /// latest value once the burst is over.
+    Both,
+}
+
+/// The updates of the current debounce burst.
+#[derive(Default)]
+struct Burst {
+    /// Number of updates so far, so a wait knows whether another restarted it
+    updates: usize,
+    /// Whether a burst is in progress
+    active: bool,
+    /// Whether an update followed the first one of the burst
+    trailing: bool,
+}
+
+/// Whether a throttle window is open, and whether a value arrived during it.
+#[derive(Default)]
+struct Window {
+    open: bool,
+    trailing: bool,
+}
+
+impl<T: Clone + Send + Sync + 'static> Limiter<T, Mutex<Window>> {
+    /// Ends the current window, emitting the trailing value if there is one.
+    fn close(self: Arc<Self>) {
+        let mut window = self.state.lock();
+        if !window.trailing {
+            window.open = false;
+            return;
+        }
+        window.trailing = false;
+        drop(window);
+        self.emit();
+        self.after(Self::close);
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Signal<T> {
+    /// Creates a signal that takes the value of this one once it has not
+    /// changed for `duration`.
+    ///
+    /// Every update restarts the wait, so a burst of updates results in a single
+    /// update carrying the latest value. `schedule` is called with a delay and a
+    /// callback, and must run the callback after that delay without blocking,
+    /// for example by spawning a sleeping task on your runtime. This is
+    /// `debounce_edge` with `Edge::Trailing`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::{thread, time::Duration};
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let input = Signal::new(0);
+    /// let settled = input.debounce(Duration::from_millis(20), |delay, callback| {
+    ///     thread::spawn(move || {
+    ///         thread::sleep(delay);
+    ///         callback();
+    ///     });
+    /// });
+    ///
+    /// for i in 1..=3 {
+    ///     input.send(i);
+    /// }
+    /// assert_eq!(settled.get(), 0);
+    /// thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(settled.get(), 3);
+    /// ```
+    pub fn debounce(
+        &self,
+        duration: Duration,
+        schedule: impl Fn(Duration, Callback) + Send + Sync + 'static,
+    ) -> Signal<T> {
+        self.debounce_edge(duration, Edge::Trailing, schedule)
+    }
+
+    /// Creates a signal that takes the value of this one at the given `edge`
+    /// of each burst of updates.
+    ///
+    /// A burst lasts until this signal has not changed for `duration`, and
+    /// every update restarts the wait. With `Edge::Leading` the first update
+    /// of a burst is taken right away and the rest are ignored, which suits
+    /// acting on a button press while ignoring a double click. With
+    /// `Edge::Both` the first update is taken right away as well, and then
+    /// the final value once the burst is over, unless the burst was that one
+    /// update alone. `schedule` works as for `debounce`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use std::time::Duration;
+    /// use reactivity::{sync::Signal, timer::{Callback, Edge}};
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// // Callbacks are run by hand here, standing in for a timer
+    /// let pending = Arc::new(Mutex::new(Vec::<Callback>::new()));
+    /// let pending_ = pending.clone();
+    /// let schedule = move |_, callback| pending_.lock().unwrap().push(callback);
+    /// let presses = Signal::new(0);
+    /// let handled = presses.debounce_edge(Duration::from_millis(300), Edge::Both, schedule);
+    ///
+    /// presses.send(1);
+    /// presses.send(2);
+    /// presses.send(3);
+    /// assert_eq!(handled.get(), 1);
+    ///
+    /// let callbacks = std::mem::take(&mut *pending.lock().unwrap());
+    /// callbacks.into_iter().for_each(|callback| callback());
+    /// assert_eq!(handled.get(), 3);
+    /// assert_eq!(handled.update_count(), 2);
+    /// ```
+    pub fn debounce_edge(
+        &self,
+        duration: Duration,
+        edge: Edge,
+        schedule: impl Fn(Duration, Callback) + Send + Sync + 'static,
+    ) -> Signal<T> {
+        let limiter = Limiter::new(self, duration, schedule, Mutex::new(Burst::default()));
+        limiter.attach(move |limiter| {
+            let mut burst = limiter.state.lock();
+            burst.updates += 1;
+            let (update, leading) = (burst.updates, !burst.active);
+            burst.active = true;
+            burst.trailing |= !leading;
+            drop(burst);
+            limiter.after(move |limiter| {
+                let mut burst = limiter.state.lock();
+                if burst.updates != update {
+                    return;
+                }
+                burst.active = false;
+                let trailing = mem::take(&mut burst.trailing);
+                drop(burst);
+                if edge == Edge::Trailing || (edge == Edge::Both && trailing) {
+                    limiter.emit();
+                }
+            });
+            (leading && edge != Edge::Trailing).then(|| limiter.source.get())
+        })
+    }
+
+    /// Creates a signal that takes the value of this one at most once per
+    /// `duration`.
+    ///
+    /// The first update is taken immediately and opens a window. Updates during
+    /// the window are held back, and when it ends the latest of them is taken,
+    /// opening the next window. The final value is therefore never lost.
+    /// `schedule` works as for `debounce`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::{thread, time::Duration};
+    /// use reactivity::sync::Signal;
+    /// use reactivity::api::SignalTrait;
+    ///
+    /// let position = Signal::new(0);
+    /// let sampled = position.throttle(Duration::from_millis(20), |delay, callback| {
+    ///     thread::spawn(move || {
+    ///         thread::sleep(delay);
+    ///         callback();
+    ///     });
+    /// });
+    ///
+    /// for i in 1..=3 {
+    ///     position.send(i);
+    /// }
+    /// assert_eq!(sampled.get(), 1);
+    /// thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(sampled.get(), 3);
+    /// ```
+    pub fn throttle(
+        &self,
+        duration: Duration,
+        schedule: impl Fn(Duration, Callback) + Send + Sync + 'static,
+    ) -> Signal<T> {
+        let limiter = Limiter::new(self, duration, schedule, Mutex::new(Window::default()));
+        limiter.attach(|limiter| {
+            let mut window = limiter.state.lock();
+            if window.open {
+                window.trailing = true;
+                return None;
+            }
+            window.open = true;
+            drop(window);
+            limiter.after(Limiter::close);
+            Some(limiter.source.get())
+        })
+    }
+}